@@ -0,0 +1,76 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gkquad::single::Integrator;
+use nalgebra::{Matrix3, Vector3};
+use server::channels::{CurvatureSplines, PointSlice};
+use std::f64::consts::PI;
+
+/// Curvature of y = cos(x) at x.
+fn cos_curvature(x: f64) -> f64 {
+    x.cos() / (1. + x.sin().powi(2)).powi(3).sqrt()
+}
+
+/// Arc length of y = cos(x) between 0 and x.
+fn cos_s(x: f64) -> f64 {
+    Integrator::new(|x: f64| (1. + x.sin().powi(2)).sqrt())
+        .run(0.0..x)
+        .estimate()
+        .unwrap()
+}
+
+/// Frenet reconstruction as it was before propagation on SO(3):
+/// generalized inverse of rotation matrix at every step, positions only.
+#[rustfmt::skip]
+fn pseudo_inverse_reconstruct(splines: &CurvatureSplines, mut ai: Vector3<f64>, mut ri: Matrix3<f64>) -> Vec<Vector3<f64>> {
+    let mut points = Vec::with_capacity(splines.splines.len());
+    let mut alpha_last = 0.;
+    for ((ka, kb), ds) in splines.splines.iter().cloned().zip(splines.steps().iter().cloned()) {
+        if ka == 0. && kb == 0. {
+            ai += ri.pseudo_inverse(0.000000001).unwrap() * Vector3::new(0., 0., ds);
+        } else {
+            let k = (ka.powi(2) + kb.powi(2)).sqrt();
+            let theta = k * ds;
+            let alpha = (ka / k).acos();
+            let phi = alpha - alpha_last;
+            alpha_last = alpha;
+            ri = Matrix3::new(
+                phi.cos(), -phi.sin(), 0.,
+                phi.sin(), phi.cos(), 0.,
+                0., 0., 1.,
+            ) * ri;
+            ai += ri.pseudo_inverse(0.000000001).unwrap() * Vector3::new((1. - theta.cos()) / k, 0., theta.sin() / k);
+            ri = Matrix3::new(
+                theta.cos(), 0., theta.sin(),
+                0., 1., 0.,
+                -theta.sin(), 0., theta.cos(),
+            ) * ri;
+        }
+        points.push(ai);
+    }
+    points
+}
+
+fn rotation_propagation(c: &mut Criterion) {
+    let splines = (0..9) // nine sample points.
+        .map(|i| i as f64 * 2. * PI / 8.)
+        .map(|x| (cos_s(x), cos_curvature(x), cos_curvature(x) / 2.))
+        .collect::<Vec<_>>()
+        .interpolate(0.01)
+        .unwrap();
+    let ai = Vector3::new(0., 0., 1.);
+    let ri = Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.);
+
+    let mut group = c.benchmark_group("rotation propagation");
+    group.bench_function("pseudo inverse", |b| {
+        b.iter(|| pseudo_inverse_reconstruct(black_box(&splines), ai, ri))
+    });
+    group.bench_function("frenet", |b| {
+        b.iter(|| black_box(&splines).frenet_reconstruct(ai, ri).unwrap())
+    });
+    group.bench_function("curvature", |b| {
+        b.iter(|| black_box(&splines).curvature_reconstruct(ai, ri).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, rotation_propagation);
+criterion_main!(benches);
@@ -1,86 +1,584 @@
+pub mod anchor;
+pub mod calibration;
+pub mod device;
+pub mod fbg;
+pub mod filter;
+pub mod impute;
+pub mod interpolation;
+pub mod inverse;
+pub mod metrics;
 pub mod mock;
+pub mod protocol;
+pub mod reconstructor;
+pub mod rod;
+pub mod shape;
+pub mod structure;
+pub mod tracker;
+pub mod uncertainty;
 pub mod ws_channel;
 
 mod curvature_splines;
+mod error;
+
+pub use curvature_splines::{CurvatureSplines, PointSlice, Step};
+
+use crate::curve::{Closure, Curve, FrameFormat, MultiCurve, Point};
+use anchor::{Anchor, Solved};
 use async_std::sync::{Mutex, RwLock};
+use calibration::Calibration;
+use device::Device;
+use error::ReconstructError;
+use fbg::StrainModel;
+use filter::{Filter, FilterState};
 use futures::stream::SplitSink;
 use futures::SinkExt;
-use log::error;
+use impute::Imputation;
+use interpolation::Scheme;
+use log::{error, warn};
+use nalgebra::{Matrix3, Vector3};
+use num::{one, zero};
+use protocol::{Downstream, Encoding, Header, Length};
+use reconstructor::{Frenet, Reconstructor, Registry};
 use roa::http::StatusCode;
 use roa::websocket::{Message, SocketStream};
 use roa::{status, Result};
+use shape::{Pose, ReconstructedCurve};
 use slab::Slab;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use structure::Structure;
+use tracker::Tracker;
+use uncertainty::NoiseModel;
+
+/// Number of frames before the last one, within which a frame is taken as late rather than restarted.
+const SEQ_WINDOW: u64 = 64;
 
 type Sender = SplitSink<SocketStream, Message>;
-type Channel = Slab<Mutex<Sender>>;
+type Channel = Slab<Subscriber>;
+// points, with closure and correction solved if corrected to a target of tip
+type Anchored = (Vec<Point>, Option<(Closure, Solved)>);
 
+/// A subscriber of a channel, with the encoding of curves it chose.
+struct Subscriber {
+    sender: Mutex<Sender>,
+    encoding: Encoding,
+}
+
+/// Reconstruction settings of a channel, chosen when the channel is created.
 #[derive(Clone)]
-pub struct SyncChannel(Arc<RwLock<Channel>>);
+pub struct ChannelConfig {
+    pub reconstructor: Arc<dyn Reconstructor>,
+    pub scheme: Scheme,
+    // converts strain of cores to curvature, if source sends strain
+    pub strain: Option<StrainModel>,
+    // policy of filling missing values of sensors
+    pub imputation: Imputation,
+    // initial calibration of sensors
+    pub calibration: Calibration,
+    // temporal filter of calibrated curvature
+    pub filter: Filter,
+    // physical geometry of device beyond sensors
+    pub device: Device,
+    // noise of sensors, to estimate uncertainty of shape
+    pub noise: Option<NoiseModel>,
+    // orientation emitted with each point
+    pub frame: FrameFormat,
+    // delta s, fixed or adaptive
+    pub step: Step,
+    // unit of length of settings and curves, to which samples are converted
+    pub unit: Option<Length>,
+    // target of tip, to which a single curve is corrected
+    pub anchor: Option<Anchor>,
+    // tree of segments, if source sends one frame per segment
+    pub structure: Option<Structure>,
+    // initialized coordinate
+    pub ai: Vector3<f64>,
+    // initialized rotation matrix
+    pub ri: Matrix3<f64>,
+}
+
+/// Mutable state of a channel, updated by frames and commands from source.
+///
+/// A channel lives as long as its source connection, so the state is reset on reconnection.
+struct ChannelState {
+    calibration: Calibration,
+    filter: FilterState,
+    // last raw frame reconstructed after imputation, the baseline of tare
+    last_frame: Option<Vec<(f64, f64, f64)>>,
+    // time of last frame reconstructed
+    last_time: Option<Instant>,
+    // base poses from tracker, replace `ai` and `ri` of config if any
+    tracker: Tracker,
+    // sequence number of last frame reconstructed
+    last_seq: Option<u64>,
+}
+
+/// A frame received and preprocessed, to be committed to state once reconstructed.
+struct Prepared {
+    timestamp: u64,
+    now: Instant,
+    ai: Vector3<f64>,
+    ri: Matrix3<f64>,
+    // number of stations of each segment
+    lengths: Vec<usize>,
+    // raw frame after imputation, the baseline of tare
+    raw: Vec<(f64, f64, f64)>,
+    // calibrated and filtered frame
+    filtered: Vec<(f64, f64, f64)>,
+    filter: FilterState,
+    imputed: Vec<usize>,
+}
 
 #[derive(Clone)]
-pub struct SyncChannels(Arc<RwLock<Slab<SyncChannel>>>);
+pub struct SyncChannel {
+    subscribers: Arc<RwLock<Channel>>,
+    config: Arc<ChannelConfig>,
+    state: Arc<Mutex<ChannelState>>,
+}
+
+#[derive(Clone)]
+pub struct SyncChannels {
+    channels: Arc<RwLock<Slab<SyncChannel>>>,
+    registry: Arc<Registry>,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            reconstructor: Arc::new(Frenet),
+            scheme: Scheme::Linear,
+            strain: None,
+            imputation: Imputation::Reject,
+            calibration: Calibration::default(),
+            filter: Filter::None,
+            device: Device::default(),
+            noise: None,
+            frame: FrameFormat::None,
+            step: Step::Fixed(0.05),
+            unit: None,
+            anchor: None,
+            structure: None,
+            ai: zero(),
+            ri: one(),
+        }
+    }
+}
+
+impl ChannelConfig {
+    /// Interpolate raw data, then reconstruct curve.
+    pub fn reconstruct(
+        &self,
+        data: &[(f64, f64, f64)],
+    ) -> std::result::Result<Vec<Point>, ReconstructError> {
+        let (mut points, _) =
+            self.reconstruct_splines(&self.interpolate(data)?, self.ai, self.ri)?;
+        self.frame.apply(&mut points);
+        Ok(points)
+    }
+
+    /// Interpolate raw data, then reconstruct curve for queries, with frames of any format.
+    pub fn reconstruct_curve(
+        &self,
+        data: &[(f64, f64, f64)],
+    ) -> std::result::Result<ReconstructedCurve, ReconstructError> {
+        let splines = self.interpolate(data)?;
+        let (points, _) = self.reconstruct_splines(&splines, self.ai, self.ri)?;
+        ReconstructedCurve::new(&splines, &points, self.ai, self.ri)
+    }
+
+    /// Reconstruct a segment over its device from its base pose, return its points and tip pose.
+    pub fn reconstruct_segment(
+        &self,
+        data: &[(f64, f64, f64)],
+        device: &Device,
+        base: &Pose,
+    ) -> std::result::Result<(Vec<Point>, Pose), ReconstructError> {
+        let (ai, ri) = base.to_base();
+        let splines = device.interpolate(data, self.step, self.scheme)?;
+        let mut points = self.reconstructor.reconstruct(&splines, ai, ri)?;
+        let tip = ReconstructedCurve::new(&splines, &points, ai, ri)?.tip();
+        self.frame.apply(&mut points);
+        Ok((points, tip))
+    }
+
+    /// Extend raw data over the device, then interpolate.
+    fn interpolate(
+        &self,
+        data: &[(f64, f64, f64)],
+    ) -> std::result::Result<CurvatureSplines, ReconstructError> {
+        self.device.interpolate(data, self.step, self.scheme)
+    }
+
+    /// Reconstruct curve from interpolated data, starting at position `ai` with rotation `ri`,
+    /// then correct it to the target of tip if any, with frames of any format.
+    ///
+    /// Return the closure and the correction solved if the channel has a target of tip.
+    fn reconstruct_splines(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> std::result::Result<Anchored, ReconstructError> {
+        match &self.anchor {
+            Some(anchor) => {
+                let (points, closure, solved) = anchor.reconstruct(splines, ai, ri, |splines| {
+                    self.reconstructor.reconstruct(splines, ai, ri)
+                })?;
+                Ok((points, Some((closure, solved))))
+            }
+            None => Ok((self.reconstructor.reconstruct(splines, ai, ri)?, None)),
+        }
+    }
+}
+
+impl ChannelState {
+    /// Check sequence number of a frame against the last one, and track its base pose if any.
+    ///
+    /// Return timestamp of the frame, default to now.
+    ///
+    /// A frame at most `SEQ_WINDOW` before the last one is late or repeated, and rejected;
+    /// a frame numbered 0 or further back means the source restarted its counter.
+    fn receive(&mut self, header: &Header) -> std::result::Result<u64, ReconstructError> {
+        if let (Some(seq), Some(last)) = (header.seq, self.last_seq) {
+            if seq <= last && (seq == 0 || last - seq > SEQ_WINDOW) {
+                warn!("source restarts sequence at frame {}, after {}", seq, last);
+                self.last_seq = None;
+            } else if seq <= last {
+                return Err(ReconstructError::Stale { seq, last });
+            }
+            if seq > last + 1 {
+                warn!("lost {} frames before frame {}", seq - last - 1, seq);
+            }
+        }
+        let timestamp = header.timestamp.unwrap_or_else(self::timestamp);
+        if let Some(pose) = header.pose {
+            self.tracker.push(timestamp, pose);
+        }
+        Ok(timestamp)
+    }
+
+    /// `ai` and `ri` at timestamp, tracked or configured.
+    fn base(&self, config: &ChannelConfig, timestamp: u64) -> (Vector3<f64>, Matrix3<f64>) {
+        match self.tracker.at(timestamp) {
+            Some(pose) => pose.to_base(),
+            None => (config.ai, config.ri),
+        }
+    }
+
+    /// Receive a frame of segments, impute stations of each segment,
+    /// then calibrate and filter sensors of all segments as one frame, in order of segments.
+    ///
+    /// A single curve is a frame of one segment.
+    /// Nothing but sequence and base pose is recorded until the frame is committed.
+    fn prepare(
+        &mut self,
+        config: &ChannelConfig,
+        data: &mut [Vec<(f64, f64, f64)>],
+        header: &Header,
+    ) -> std::result::Result<Prepared, ReconstructError> {
+        let timestamp = self.receive(header)?;
+        let now = Instant::now();
+        let dt = self
+            .last_time
+            .map(|time| now.duration_since(time).as_secs_f64())
+            .unwrap_or_default();
+        let (ai, ri) = self.base(config, timestamp);
+        let lengths = data.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut last = self
+            .last_frame
+            .as_deref()
+            .filter(|last| last.len() == lengths.iter().sum::<usize>());
+        let mut imputed = Vec::new();
+        let mut start = 0;
+        for segment in data.iter_mut() {
+            let (head, tail) = match last {
+                Some(last) => {
+                    let (head, tail) = last.split_at(segment.len());
+                    (Some(head), Some(tail))
+                }
+                None => (None, None),
+            };
+            last = tail;
+            imputed.extend(
+                config
+                    .imputation
+                    .apply(segment, head)
+                    .into_iter()
+                    .map(|index| index + start),
+            );
+            start += segment.len();
+        }
+        let raw = data.concat();
+        let mut filter = self.filter.clone();
+        let filtered = filter.apply(&config.filter, &self.calibration.apply(&raw)?, dt);
+        Ok(Prepared {
+            timestamp,
+            now,
+            ai,
+            ri,
+            lengths,
+            raw,
+            filtered,
+            filter,
+            imputed,
+        })
+    }
+
+    /// Record a reconstructed frame as the last one, return its timestamp and imputed stations.
+    fn commit(&mut self, frame: Prepared, header: &Header) -> (u64, Vec<usize>) {
+        self.filter = frame.filter;
+        self.last_frame = Some(frame.raw);
+        self.last_time = Some(frame.now);
+        self.last_seq = header.seq.or(self.last_seq);
+        (frame.timestamp, frame.imputed)
+    }
+}
+
+impl Prepared {
+    /// Split calibrated and filtered stations into segments.
+    fn segments(&self) -> Vec<Vec<(f64, f64, f64)>> {
+        let mut rest = self.filtered.as_slice();
+        self.lengths
+            .iter()
+            .map(|len| {
+                let (head, tail) = rest.split_at(*len);
+                rest = tail;
+                head.to_vec()
+            })
+            .collect()
+    }
+}
 
 impl SyncChannel {
-    pub fn new() -> Self {
-        Self(Arc::new(RwLock::new(Slab::new())))
+    pub fn new(config: ChannelConfig) -> Self {
+        let state = ChannelState {
+            calibration: config.calibration.clone(),
+            filter: FilterState::default(),
+            last_frame: None,
+            last_time: None,
+            tracker: Tracker::default(),
+            last_seq: None,
+        };
+        Self {
+            subscribers: Arc::new(RwLock::new(Slab::new())),
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn config(&self) -> &ChannelConfig {
+        &self.config
+    }
+
+    /// Impute, calibrate and filter raw data from source, then reconstruct curve
+    /// from the base pose at the time of frame,
+    /// with its uncertainty if the channel has a noise model.
+    ///
+    /// Frames not after the last one in sequence are rejected.
+    /// State is updated only if the frame is reconstructed, except for the base pose in header.
+    pub async fn reconstruct(
+        &self,
+        data: Vec<(f64, f64, f64)>,
+        header: &Header,
+    ) -> std::result::Result<Curve, ReconstructError> {
+        let mut state = self.state.lock().await;
+        let frame = state.prepare(&self.config, &mut [data], header)?;
+        let (ai, ri) = (frame.ai, frame.ri);
+        let splines = self.config.interpolate(&frame.filtered)?;
+        let (mut points, anchored) = self.config.reconstruct_splines(&splines, ai, ri)?;
+        self.config.frame.apply(&mut points);
+        let envelope = match &self.config.noise {
+            // perturbed curves share steps of the curve, so their points correspond,
+            // and the correction solved for the curve, so each takes a single reconstruction.
+            Some(noise) => Some(noise.envelope(
+                &frame.filtered,
+                |data| {
+                    let perturbed = self.config.device.interpolate_steps(
+                        data,
+                        splines.steps(),
+                        self.config.scheme,
+                    )?;
+                    let reconstruct = |splines: &CurvatureSplines| {
+                        self.config.reconstructor.reconstruct(splines, ai, ri)
+                    };
+                    match &anchored {
+                        Some((_, solved)) => solved.apply(&perturbed, ai, ri, reconstruct),
+                        None => reconstruct(&perturbed),
+                    }
+                },
+                &mut rand::thread_rng(),
+            )?),
+            None => None,
+        };
+        let closure = anchored.map(|(closure, _)| closure);
+        let (timestamp, imputed) = state.commit(frame, header);
+        Ok(Curve {
+            timestamp,
+            seq: header.seq,
+            points,
+            envelope,
+            imputed,
+            closure,
+        })
+    }
+
+    /// Impute, calibrate and filter raw data of each segment, then reconstruct the structure.
+    ///
+    /// Sensors of all segments are calibrated and filtered as one frame, in order of segments.
+    pub async fn reconstruct_tree(
+        &self,
+        mut data: Vec<Vec<(f64, f64, f64)>>,
+        header: &Header,
+    ) -> std::result::Result<MultiCurve, ReconstructError> {
+        let structure = self
+            .config
+            .structure
+            .as_ref()
+            .ok_or(ReconstructError::NoStructure)?;
+        let mut state = self.state.lock().await;
+        let frame = state.prepare(&self.config, &mut data, header)?;
+        let base = Pose::from_base(frame.ai, frame.ri)?;
+        let segments = structure.reconstruct(&base, &frame.segments(), |segment, data, base| {
+            self.config.reconstruct_segment(data, &segment.device, base)
+        })?;
+        let (timestamp, imputed) = state.commit(frame, header);
+        Ok(MultiCurve {
+            timestamp,
+            seq: header.seq,
+            segments,
+            imputed,
+        })
     }
 
+    /// Record a base pose from tracker at timestamp in milliseconds, default to now.
+    pub async fn track(&self, pose: Pose, timestamp: Option<u64>) {
+        let timestamp = timestamp.unwrap_or_else(self::timestamp);
+        self.state.lock().await.tracker.push(timestamp, pose);
+    }
+
+    /// Record the last raw frame as the straight baseline.
+    ///
+    /// Return false if no frame has been reconstructed.
+    pub async fn tare(&self) -> bool {
+        let mut state = self.state.lock().await;
+        let ChannelState {
+            calibration,
+            filter,
+            last_frame,
+            ..
+        } = &mut *state;
+        match last_frame {
+            Some(frame) => {
+                calibration.tare(frame);
+                filter.reset();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Send the same message to all subscribers, whatever their encoding.
+    #[allow(dead_code)]
     pub async fn broadcast(&self, message: Message) {
-        let channel = self.0.read().await;
+        self.send_all(|_| message.clone()).await
+    }
+
+    /// Send a curve to each subscriber in its encoding, encoded once per encoding.
+    pub async fn publish<T: Downstream>(&self, curve: &T) {
+        let (mut json, mut binary) = (None, None);
+        self.send_all(|encoding| {
+            let message = match encoding {
+                Encoding::Json => &mut json,
+                Encoding::Binary => &mut binary,
+            };
+            message
+                .get_or_insert_with(|| Message::Binary(encoding.encode(curve)))
+                .clone()
+        })
+        .await
+    }
+
+    /// Send message of each encoding to subscribers, removing those broken.
+    async fn send_all(&self, mut message: impl FnMut(Encoding) -> Message) {
         let mut broken_sender = Vec::new();
-        for (index, sender) in channel.iter() {
-            if let Err(err) = sender.lock().await.send(message.clone()).await {
+        for (index, subscriber) in self.subscribers.read().await.iter() {
+            let message = message(subscriber.encoding);
+            if let Err(err) = subscriber.sender.lock().await.send(message).await {
                 error!("broadcast error: {}", err);
                 broken_sender.push(index);
             }
         }
         for index in broken_sender {
-            self.0.write().await.remove(index);
+            self.subscribers.write().await.remove(index);
         }
     }
 
     #[allow(dead_code)]
     pub async fn send(&self, index: usize, message: Message) {
-        if let Err(err) = self.0.read().await[index].lock().await.send(message).await {
+        if let Err(err) = self.subscribers.read().await[index]
+            .sender
+            .lock()
+            .await
+            .send(message)
+            .await
+        {
             error!("message send error: {}", err)
         }
     }
 
-    pub async fn register(&self, sender: Sender) -> usize {
-        self.0.write().await.insert(Mutex::new(sender))
+    pub async fn register(&self, sender: Sender, encoding: Encoding) -> usize {
+        self.subscribers.write().await.insert(Subscriber {
+            sender: Mutex::new(sender),
+            encoding,
+        })
     }
 
     pub async fn deregister(&self, index: usize) -> Option<Sender> {
-        let mut channel = self.0.write().await;
+        let mut channel = self.subscribers.write().await;
         if channel.contains(index) {
-            Some(channel.remove(index).into_inner())
+            Some(channel.remove(index).sender.into_inner())
         } else {
             None
         }
     }
 
     pub async fn deregister_all(&self) {
-        for sender in self.0.write().await.drain() {
-            if let Err(err) = sender.lock().await.close().await {
+        for subscriber in self.subscribers.write().await.drain() {
+            if let Err(err) = subscriber.sender.lock().await.close().await {
                 error!("error in close websocket: {}", err)
             }
         }
     }
 }
 
+/// Milliseconds since unix epoch.
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
 impl SyncChannels {
-    pub fn new() -> Self {
-        Self(Arc::new(RwLock::new(Slab::new())))
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            channels: Arc::new(RwLock::new(Slab::new())),
+            registry: Arc::new(registry),
+        }
     }
 
-    pub async fn new_channel(&self) -> (usize, SyncChannel) {
-        let channel = SyncChannel::new();
-        (self.0.write().await.insert(channel.clone()), channel)
+    /// Get reconstructor by name.
+    pub fn reconstructor(&self, name: &str) -> Option<Arc<dyn Reconstructor>> {
+        self.registry.get(name)
+    }
+
+    pub async fn new_channel(&self, config: ChannelConfig) -> (usize, SyncChannel) {
+        let channel = SyncChannel::new(config);
+        (self.channels.write().await.insert(channel.clone()), channel)
     }
 
     pub async fn get_channel(&self, index: usize) -> Result<SyncChannel> {
-        match self.0.read().await.get(index) {
+        match self.channels.read().await.get(index) {
             Some(channel) => Ok(channel.clone()),
             None => Err(status!(
                 StatusCode::NOT_FOUND,
@@ -90,7 +588,149 @@ impl SyncChannels {
     }
 
     pub async fn remove_channel(&self, index: usize) {
-        let channel = self.0.write().await.remove(index);
+        let channel = self.channels.write().await.remove(index);
         channel.deregister_all().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::anchor::{Anchor, Correction, Target};
+    use super::protocol::Header;
+    use super::reconstructor::Reconstructor;
+    use super::uncertainty::NoiseModel;
+    use super::{ChannelConfig, CurvatureSplines, SyncChannel, SEQ_WINDOW};
+    use crate::channels::error::ReconstructError;
+    use crate::curve::Point;
+    use async_std::task::block_on;
+    use nalgebra::{Matrix3, Vector3};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Frenet reconstruction, counting reconstructions.
+    struct Counting(AtomicUsize);
+
+    impl Reconstructor for Counting {
+        fn reconstruct(
+            &self,
+            splines: &CurvatureSplines,
+            ai: Vector3<f64>,
+            ri: Matrix3<f64>,
+        ) -> Result<Vec<Point>, ReconstructError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            splines.frenet_reconstruct(ai, ri)
+        }
+    }
+
+    #[test]
+    fn sequence() {
+        let channel = SyncChannel::new(ChannelConfig::default());
+        let mut state = block_on(channel.state.lock());
+        let mut receive = |seq: u64| {
+            let header = Header {
+                seq: Some(seq),
+                ..Header::default()
+            };
+            let result = state.receive(&header).map(|_| ());
+            // record the frame as reconstructed.
+            if result.is_ok() {
+                state.last_seq = Some(seq);
+            }
+            result
+        };
+        assert_eq!(Ok(()), receive(1000));
+        assert_eq!(Ok(()), receive(1003));
+        assert_eq!(
+            Err(ReconstructError::Stale {
+                seq: 1003,
+                last: 1003
+            }),
+            receive(1003)
+        );
+        assert_eq!(
+            Err(ReconstructError::Stale {
+                seq: 1001,
+                last: 1003
+            }),
+            receive(1001)
+        );
+        assert!(receive(1003 - SEQ_WINDOW).is_err());
+
+        // firmware reboots and counts from 0, or from a value far behind.
+        assert_eq!(Ok(()), receive(0));
+        assert_eq!(Ok(()), receive(1));
+        assert_eq!(Ok(()), receive(500));
+        assert_eq!(Ok(()), receive(500 - SEQ_WINDOW - 1));
+    }
+
+    #[test]
+    fn tree_without_structure() {
+        let channel = SyncChannel::new(ChannelConfig::default());
+        let data = vec![vec![(0., 0., 0.), (1., 0., 0.)]];
+        assert_eq!(
+            Some(ReconstructError::NoStructure),
+            block_on(channel.reconstruct_tree(data, &Header::default())).err()
+        );
+    }
+
+    #[test]
+    fn anchored_envelope() {
+        let counting = Arc::new(Counting(AtomicUsize::new(0)));
+        let config = ChannelConfig {
+            reconstructor: counting.clone(),
+            anchor: Some(Anchor {
+                target: Target {
+                    position: Some(Vector3::new(-1.8, 0., 2.)),
+                    orientation: None,
+                },
+                correction: Correction::Curvature,
+            }),
+            ..ChannelConfig::default()
+        };
+        let data = vec![(0., 0.4, 0.), (1.5, 0.4, 0.), (3., 0.4, 0.)];
+        let reconstruct = |config: ChannelConfig| {
+            block_on(SyncChannel::new(config).reconstruct(data.clone(), &Header::default()))
+                .unwrap()
+        };
+        let curve = reconstruct(config.clone());
+        assert!(curve.envelope.is_none());
+        let solving = counting.0.swap(0, Ordering::SeqCst);
+        assert!(solving > 16);
+
+        // perturbed curves reuse the correction of the curve, one reconstruction each.
+        let curve = reconstruct(ChannelConfig {
+            noise: Some(NoiseModel {
+                sensors: vec![(1e-3, 1e-3)],
+                samples: 16,
+                confidence: 0.95,
+                covariance: false,
+            }),
+            ..config
+        });
+        assert_eq!(solving + 16, counting.0.load(Ordering::SeqCst));
+        let tip = curve.points.last().unwrap();
+        let mean = curve.envelope.unwrap().mean[curve.points.len() - 1];
+        let distance = Vector3::new(tip.x - mean[0], tip.y - mean[1], tip.z - mean[2]).norm();
+        assert!(distance < 1e-2, "{}", distance);
+    }
+
+    #[test]
+    fn anchored_curve() {
+        let config = ChannelConfig {
+            anchor: Some(Anchor {
+                target: Target {
+                    position: Some(Vector3::new(-1.8, 0., 2.)),
+                    orientation: None,
+                },
+                correction: Correction::Curvature,
+            }),
+            ..ChannelConfig::default()
+        };
+        let data = [(0., 0.4, 0.), (1.5, 0.4, 0.), (3., 0.4, 0.)];
+        let points = config.reconstruct(&data).unwrap();
+        let tip = config.reconstruct_curve(&data).unwrap().tip().position;
+        let last = points.last().unwrap();
+        let distance = (tip - Vector3::new(last.x, last.y, last.z).map(f64::from)).norm();
+        assert!(distance < 1e-5, "{}", distance);
+    }
+}
@@ -0,0 +1,483 @@
+use super::curvature_splines::CurvatureSplines;
+use super::error::ReconstructError;
+use super::shape::{Pose, ReconstructedCurve};
+use crate::curve::{Closure, Frame, Point, TipError};
+use nalgebra::{DMatrix, DVector, Matrix3, Matrix6, Quaternion, UnitQuaternion, Vector3, Vector6};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Maximum iterations of curvature correction.
+const MAX_ITERATIONS: usize = 20;
+
+/// Times a correction step is halved before giving up.
+const MAX_HALVING: usize = 8;
+
+/// Step of finite difference, in unit of curvature times length,
+/// large enough for points in single precision.
+const DIFFERENCE_STEP: f64 = 1e-4;
+
+/// Method spreading the closure error along the curve.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Correction {
+    /// The smallest correction of curvature, quadratic in arc length, reaching the target.
+    /// Roll of the tip around its tangent cannot be corrected without twist.
+    #[default]
+    Curvature,
+
+    /// Rigid correction of the tip, blended from the base by arc length.
+    Blend,
+}
+
+/// Known pose of the tip, or a part of it.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Target {
+    pub position: Option<Vector3<f64>>,
+
+    /// rotation of local frame of the tip to absolute frame.
+    pub orientation: Option<UnitQuaternion<f64>>,
+}
+
+/// Correction reaching the target of tip, solved for a curve,
+/// to correct curves of perturbed curvature alike without solving again.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Solved {
+    /// coefficients of `a0 + a1 u + a2 u^2` added to ka, then to kb.
+    Curvature(Vector6<f64>),
+
+    /// rigid correction of the tip about the base, blended by arc length.
+    Blend {
+        rotation: UnitQuaternion<f64>,
+        translation: Vector3<f64>,
+    },
+}
+
+/// Target of the tip and the correction reaching it.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Anchor {
+    pub target: Target,
+    pub correction: Correction,
+}
+
+impl Target {
+    /// Error of a tip against the target.
+    fn error(&self, tip: &Pose) -> TipError {
+        TipError {
+            position: self
+                .position
+                .map(|position| (tip.position - position).norm()),
+            angle: self
+                .orientation
+                .map(|orientation| tip.orientation.angle_to(&orientation)),
+        }
+    }
+
+    /// Residuals of position, and of tangent in the target frame scaled by length.
+    fn residuals(&self, tip: &Pose, length: f64) -> DVector<f64> {
+        let mut residuals = Vec::with_capacity(5);
+        if let Some(position) = self.position {
+            residuals.extend((tip.position - position).iter());
+        }
+        if let Some(orientation) = self.orientation {
+            let tangent = orientation.inverse() * tip.tangent();
+            residuals.extend(&[tangent.x * length, tangent.y * length]);
+        }
+        DVector::from_vec(residuals)
+    }
+}
+
+impl Anchor {
+    /// Reconstruct curve from splines by `reconstruct`, starting at position `ai` with rotation `ri`,
+    /// then correct it toward the target of tip.
+    ///
+    /// Return the correction solved too, to correct perturbed curves of the same frame.
+    pub fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+        reconstruct: impl Fn(&CurvatureSplines) -> Result<Vec<Point>, ReconstructError>,
+    ) -> Result<(Vec<Point>, Closure, Solved), ReconstructError> {
+        let points = reconstruct(splines)?;
+        let tip = ReconstructedCurve::new(splines, &points, ai, ri)?.tip();
+        let initial = self.target.error(&tip);
+        let (points, tip, solved) = match self.correction {
+            Correction::Curvature => {
+                self.correct_curvature(splines, ai, ri, reconstruct, points, tip)?
+            }
+            Correction::Blend => {
+                let base = Pose::from_base(ai, ri)?;
+                let (rotation, translation) = self.blend(&base, &tip);
+                let mut points = points;
+                blend(&base, rotation, translation, splines, &mut points);
+                (
+                    points,
+                    blend_pose(&base, rotation, translation, &tip, 1.),
+                    Solved::Blend {
+                        rotation,
+                        translation,
+                    },
+                )
+            }
+        };
+        Ok((
+            points,
+            Closure {
+                initial,
+                remaining: self.target.error(&tip),
+            },
+            solved,
+        ))
+    }
+
+    /// Correct curvature by `a0 + a1 u + a2 u^2` of ka and kb, u = s / length,
+    /// keeping the integral of squared correction small, by Gauss-Newton iterations.
+    fn correct_curvature(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+        reconstruct: impl Fn(&CurvatureSplines) -> Result<Vec<Point>, ReconstructError>,
+        points: Vec<Point>,
+        tip: Pose,
+    ) -> Result<(Vec<Point>, Pose, Solved), ReconstructError> {
+        let length = splines.length();
+        let solve = |x: &Vector6<f64>| -> Result<(Vec<Point>, Pose), ReconstructError> {
+            let corrected = correct(splines, x);
+            let points = reconstruct(&corrected)?;
+            let tip = ReconstructedCurve::new(&corrected, &points, ai, ri)?.tip();
+            Ok((points, tip))
+        };
+
+        // gram matrix of basis over arc length, the metric of correction.
+        let mut gram = Matrix6::zeros();
+        let mut s = 0.;
+        for ds in splines.steps() {
+            let u = basis(s / length);
+            let block = u * u.transpose() * *ds;
+            for (i, j) in (0..3).flat_map(|i| (0..3).map(move |j| (i, j))) {
+                gram[(i, j)] += block[(i, j)];
+                gram[(i + 3, j + 3)] += block[(i, j)];
+            }
+            s += ds;
+        }
+        let inverse = gram.try_inverse().unwrap_or_else(Matrix6::identity);
+
+        let mut x = Vector6::zeros();
+        let mut best = (points, tip);
+        let mut residuals = self.target.residuals(&tip, length);
+        for _ in 0..MAX_ITERATIONS {
+            if residuals.norm() <= 1e-9 * length {
+                break;
+            }
+            let h = DIFFERENCE_STEP / length;
+            let mut jacobian = DMatrix::zeros(residuals.len(), 6);
+            for i in 0..6 {
+                let mut perturbed = x;
+                perturbed[i] += h;
+                let (_, tip) = solve(&perturbed)?;
+                jacobian.set_column(i, &((self.target.residuals(&tip, length) - &residuals) / h));
+            }
+            // the smallest step in metric of gram satisfying the linearized target,
+            // so the first step from zero is the smallest correction of the linearized problem.
+            // curvature cannot move the tip along the curve much, so tiny singular values are dropped.
+            let projected = &jacobian * inverse * jacobian.transpose();
+            let eps = 1e-9 * projected.trace();
+            let rhs = -&residuals;
+            let y = match projected.svd(true, true).solve(&rhs, eps) {
+                Ok(y) => y,
+                Err(_) => break,
+            };
+            let step = inverse * jacobian.transpose() * y;
+            let mut step = Vector6::from_iterator(step.iter().cloned());
+
+            let mut improved = false;
+            for _ in 0..MAX_HALVING {
+                let candidate = x + step;
+                let (points, tip) = solve(&candidate)?;
+                let candidate_residuals = self.target.residuals(&tip, length);
+                if candidate_residuals.norm() < residuals.norm() {
+                    x = candidate;
+                    residuals = candidate_residuals;
+                    best = (points, tip);
+                    improved = true;
+                    break;
+                }
+                step /= 2.;
+            }
+            if !improved {
+                break;
+            }
+        }
+        let (points, tip) = best;
+        Ok((points, tip, Solved::Curvature(x)))
+    }
+
+    /// Rotation about the base and translation moving the tip onto the target.
+    fn blend(&self, base: &Pose, tip: &Pose) -> (UnitQuaternion<f64>, Vector3<f64>) {
+        let rotation = match self.target.orientation {
+            Some(orientation) => orientation * tip.orientation.inverse(),
+            None => UnitQuaternion::identity(),
+        };
+        let translation = match self.target.position {
+            Some(position) => position - base.position - rotation * (tip.position - base.position),
+            None => Vector3::zeros(),
+        };
+        (rotation, translation)
+    }
+}
+
+impl Solved {
+    /// Reconstruct curve from splines by `reconstruct`, starting at position `ai` with rotation `ri`,
+    /// then correct it alike, e.g. a perturbed curve, by a single reconstruction.
+    pub fn apply(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+        reconstruct: impl Fn(&CurvatureSplines) -> Result<Vec<Point>, ReconstructError>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        match *self {
+            Solved::Curvature(x) => reconstruct(&correct(splines, &x)),
+            Solved::Blend {
+                rotation,
+                translation,
+            } => {
+                let base = Pose::from_base(ai, ri)?;
+                let mut points = reconstruct(splines)?;
+                blend(&base, rotation, translation, splines, &mut points);
+                Ok(points)
+            }
+        }
+    }
+}
+
+/// Basis of curvature correction at u = s / length.
+fn basis(u: f64) -> Vector3<f64> {
+    Vector3::new(1., u, u * u)
+}
+
+/// Splines with `a0 + a1 u + a2 u^2` added to ka and kb, coefficients of ka first.
+fn correct(splines: &CurvatureSplines, x: &Vector6<f64>) -> CurvatureSplines {
+    let length = splines.length();
+    let mut corrected = splines.clone();
+    let mut s = 0.;
+    for ((ka, kb), ds) in corrected.splines.iter_mut().zip(splines.steps()) {
+        let phi = basis(s / length);
+        *ka += phi.dot(&Vector3::new(x[0], x[1], x[2]));
+        *kb += phi.dot(&Vector3::new(x[3], x[4], x[5]));
+        s += ds;
+    }
+    corrected
+}
+
+/// Pose moved by a rigid correction about the base, scaled by t.
+fn blend_pose(
+    base: &Pose,
+    rotation: UnitQuaternion<f64>,
+    translation: Vector3<f64>,
+    pose: &Pose,
+    t: f64,
+) -> Pose {
+    let rotation = UnitQuaternion::from_scaled_axis(rotation.scaled_axis() * t);
+    Pose {
+        position: base.position + rotation * (pose.position - base.position) + translation * t,
+        orientation: rotation * pose.orientation,
+    }
+}
+
+/// Move each point by a rigid correction about the base, scaled by its arc length over the length.
+fn blend(
+    base: &Pose,
+    rotation: UnitQuaternion<f64>,
+    translation: Vector3<f64>,
+    splines: &CurvatureSplines,
+    points: &mut [Point],
+) {
+    let length = splines.length();
+    let mut s = 0.;
+    for (point, ds) in points.iter_mut().zip(splines.steps()) {
+        s += ds;
+        let orientation = point.frame.map(|frame| {
+            let q = frame.rotation().coords;
+            UnitQuaternion::from_quaternion(Quaternion::new(
+                q[3] as f64,
+                q[0] as f64,
+                q[1] as f64,
+                q[2] as f64,
+            ))
+        });
+        let pose = blend_pose(
+            base,
+            rotation,
+            translation,
+            &Pose {
+                position: Vector3::new(point.x as f64, point.y as f64, point.z as f64),
+                orientation: orientation.unwrap_or_else(UnitQuaternion::identity),
+            },
+            s / length,
+        );
+        point.x = pose.position.x as f32;
+        point.y = pose.position.y as f32;
+        point.z = pose.position.z as f32;
+        if orientation.is_some() {
+            point.frame = Some(Frame::from_rotation(&pose.orientation));
+        }
+    }
+}
+
+impl FromStr for Correction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "curvature" => Ok(Correction::Curvature),
+            "blend" => Ok(Correction::Blend),
+            _ => Err(format!("unknown closure correction: {}", s)),
+        }
+    }
+}
+
+impl Display for Correction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Correction::Curvature => "curvature",
+            Correction::Blend => "blend",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Anchor, Correction, Target};
+    use crate::channels::curvature_splines::{CurvatureSplines, PointSlice};
+    use crate::channels::metrics::{evaluate, positions};
+    use crate::curve::Closure;
+    use nalgebra::{UnitQuaternion, Vector3};
+    use num::{One, Zero};
+    use std::cell::Cell;
+
+    /// Arc of curvature k from the origin toward z, bending toward -x.
+    fn arc(k: f64, s: f64) -> Vector3<f64> {
+        Vector3::new(-(1. - (k * s).cos()) / k, 0., (k * s).sin() / k)
+    }
+
+    /// Target of an arc of curvature 0.5 and length 3.
+    fn target(position: bool, orientation: bool) -> Target {
+        Target {
+            position: if position { Some(arc(0.5, 3.)) } else { None },
+            orientation: if orientation {
+                Some(UnitQuaternion::from_axis_angle(&-Vector3::y_axis(), 1.5))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Rms error against the arc, of curve reconstructed from underestimated curvature.
+    fn reconstruct(anchor: &Anchor) -> (f64, Closure) {
+        let splines = [(0., 0.4, 0.05), (3., 0.4, 0.05)]
+            .interpolate(0.05)
+            .unwrap();
+        let (points, closure, _) = anchor
+            .reconstruct(&splines, Zero::zero(), One::one(), |splines| {
+                splines.frenet_reconstruct(Zero::zero(), One::one())
+            })
+            .unwrap();
+        let mut curve = vec![Vector3::zeros()];
+        curve.extend(positions(&points));
+        let reference = (0..=60)
+            .map(|i| arc(0.5, i as f64 * 0.05))
+            .collect::<Vec<_>>();
+        (evaluate(&curve, &reference).unwrap().rms_error, closure)
+    }
+
+    #[test]
+    fn curvature() {
+        let (free, closure) = reconstruct(&Anchor {
+            target: target(false, false),
+            ..Anchor::default()
+        });
+        assert_eq!(None, closure.initial.position);
+
+        for (position, orientation) in [(true, true), (true, false), (false, true)].iter() {
+            let (rms, closure) = reconstruct(&Anchor {
+                target: target(*position, *orientation),
+                correction: Correction::Curvature,
+            });
+            assert!(rms < free / 4., "{} {}", rms, free);
+            if *position {
+                assert!(closure.initial.position.unwrap() > 0.1, "{:?}", closure);
+                assert!(closure.remaining.position.unwrap() < 1e-5, "{:?}", closure);
+            }
+            if *orientation {
+                // roll of the tip is left without twist.
+                let initial = closure.initial.angle.unwrap();
+                assert!(initial > 0.1, "{:?}", closure);
+                assert!(
+                    closure.remaining.angle.unwrap() < initial / 10.,
+                    "{:?}",
+                    closure
+                );
+            }
+        }
+
+        // out of reach, the residual is left at the tip.
+        let (_, closure) = reconstruct(&Anchor {
+            target: Target {
+                position: Some(Vector3::new(0., 0., 4.)),
+                orientation: None,
+            },
+            correction: Correction::Curvature,
+        });
+        let remaining = closure.remaining.position.unwrap();
+        assert!(remaining > 0.9 && remaining < closure.initial.position.unwrap());
+    }
+
+    #[test]
+    fn apply() {
+        let splines = [(0., 0.4, 0.05), (3., 0.4, 0.05)]
+            .interpolate(0.05)
+            .unwrap();
+        let count = Cell::new(0);
+        let reconstruct = |splines: &CurvatureSplines| {
+            count.set(count.get() + 1);
+            splines.frenet_reconstruct(Zero::zero(), One::one())
+        };
+        for correction in [Correction::Curvature, Correction::Blend].iter() {
+            let anchor = Anchor {
+                target: target(true, true),
+                correction: *correction,
+            };
+            let (points, _, solved) = anchor
+                .reconstruct(&splines, Zero::zero(), One::one(), reconstruct)
+                .unwrap();
+
+            // the same curvature is corrected alike by a single reconstruction.
+            count.set(0);
+            let applied = solved
+                .apply(&splines, Zero::zero(), One::one(), reconstruct)
+                .unwrap();
+            assert_eq!(1, count.get());
+            let error = evaluate(&positions(&applied), &positions(&points))
+                .unwrap()
+                .max_error;
+            assert!(error < 1e-6, "{}: {}", correction, error);
+        }
+    }
+
+    #[test]
+    fn blend() {
+        let (free, _) = reconstruct(&Anchor::default());
+        let (rms, closure) = reconstruct(&Anchor {
+            target: target(true, true),
+            correction: Correction::Blend,
+        });
+        assert!(rms < free / 1.5, "{} {}", rms, free);
+        assert!(closure.remaining.position.unwrap() < 1e-9, "{:?}", closure);
+        assert!(closure.remaining.angle.unwrap() < 1e-6, "{:?}", closure);
+
+        assert_eq!(Ok(Correction::Blend), "blend".parse());
+        assert!("spline".parse::<Correction>().is_err());
+        assert_eq!("curvature", Correction::Curvature.to_string());
+    }
+}
@@ -0,0 +1,163 @@
+use super::error::ReconstructError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Calibration of one sensor station.
+///
+/// Raw reading (s, ka, kb) is corrected as
+/// `s + shift, rotate(rotation, gain * (k - offset))`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorCalibration {
+    /// gain of (ka, kb).
+    pub gain: (f64, f64),
+
+    /// raw reading of (ka, kb) when the sensor is straight.
+    pub offset: (f64, f64),
+
+    /// angle in radians from sensor axes to bending plane axes, counterclockwise.
+    pub rotation: f64,
+
+    /// correction of position along s.
+    pub shift: f64,
+}
+
+/// Per-sensor calibration of a channel, an empty one does nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub sensors: Vec<SensorCalibration>,
+}
+
+impl Default for SensorCalibration {
+    fn default() -> Self {
+        Self {
+            gain: (1., 1.),
+            offset: (0., 0.),
+            rotation: 0.,
+            shift: 0.,
+        }
+    }
+}
+
+impl SensorCalibration {
+    pub fn apply(&self, (s, ka, kb): (f64, f64, f64)) -> (f64, f64, f64) {
+        let ka = self.gain.0 * (ka - self.offset.0);
+        let kb = self.gain.1 * (kb - self.offset.1);
+        let (sin, cos) = self.rotation.sin_cos();
+        (s + self.shift, cos * ka - sin * kb, sin * ka + cos * kb)
+    }
+}
+
+impl Calibration {
+    /// Load calibration from a json file, e.g. `{"sensors": [{"gain": [1.02, 0.98]}]}`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Correct raw data, one sensor per station.
+    pub fn apply(
+        &self,
+        data: &[(f64, f64, f64)],
+    ) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+        if self.sensors.is_empty() {
+            return Ok(data.to_vec());
+        }
+        if self.sensors.len() != data.len() {
+            return Err(ReconstructError::CalibrationMismatch {
+                sensors: self.sensors.len(),
+                found: data.len(),
+            });
+        }
+        Ok(self
+            .sensors
+            .iter()
+            .zip(data.iter())
+            .map(|(sensor, raw)| sensor.apply(*raw))
+            .collect())
+    }
+
+    /// Record raw data as the straight baseline.
+    ///
+    /// Sensors are added or dropped to match stations of data.
+    pub fn tare(&mut self, data: &[(f64, f64, f64)]) {
+        self.sensors.resize(data.len(), Default::default());
+        for (sensor, (_, ka, kb)) in self.sensors.iter_mut().zip(data.iter()) {
+            sensor.offset = (*ka, *kb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Calibration, SensorCalibration};
+    use crate::channels::error::ReconstructError;
+    use std::f64::consts::PI;
+
+    const DATA: [(f64, f64, f64); 3] = [(0., 0.1, 0.), (5., 0.2, -0.1), (10., 0., 0.3)];
+
+    fn close((s1, a1, b1): (f64, f64, f64), (s2, a2, b2): (f64, f64, f64)) -> bool {
+        (s1 - s2).abs() < 1e-12 && (a1 - a2).abs() < 1e-12 && (b1 - b2).abs() < 1e-12
+    }
+
+    #[test]
+    fn apply() {
+        assert_eq!(Ok(DATA.to_vec()), Calibration::default().apply(&DATA));
+        let sensor = SensorCalibration {
+            gain: (2., 0.5),
+            offset: (0.1, -0.2),
+            rotation: PI / 2.,
+            shift: 0.3,
+        };
+        // (2 * (0.2 - 0.1), 0.5 * (-0.1 + 0.2)) = (0.2, 0.05), then rotated by 90 degrees.
+        assert!(close((5.3, -0.05, 0.2), sensor.apply(DATA[1])));
+
+        let calibration = Calibration {
+            sensors: vec![sensor; 2],
+        };
+        assert_eq!(
+            Err(ReconstructError::CalibrationMismatch {
+                sensors: 2,
+                found: 3
+            }),
+            calibration.apply(&DATA)
+        );
+    }
+
+    #[test]
+    fn tare() {
+        let mut calibration = Calibration {
+            sensors: vec![SensorCalibration {
+                gain: (2., 2.),
+                ..Default::default()
+            }],
+        };
+        calibration.tare(&DATA);
+        assert_eq!(3, calibration.sensors.len());
+        assert_eq!((2., 2.), calibration.sensors[0].gain);
+        for (calibrated, raw) in calibration.apply(&DATA).unwrap().iter().zip(DATA.iter()) {
+            assert!(close((raw.0, 0., 0.), *calibrated));
+        }
+    }
+
+    #[test]
+    fn load() {
+        let path = std::env::temp_dir().join("crow-calibration-load.json");
+        std::fs::write(
+            &path,
+            r#"{"sensors": [{"gain": [1.5, 1.0], "rotation": 0.1}, {}]}"#,
+        )
+        .unwrap();
+        let calibration = Calibration::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(2, calibration.sensors.len());
+        assert_eq!((1.5, 1.), calibration.sensors[0].gain);
+        assert_eq!(0.1, calibration.sensors[0].rotation);
+        assert_eq!(SensorCalibration::default(), calibration.sensors[1]);
+        assert!(
+            Calibration::load(std::env::temp_dir().join("crow-calibration-none.json")).is_err()
+        );
+    }
+}
@@ -1,57 +1,230 @@
-use crate::curve::Point;
-use nalgebra::{Matrix3, Vector3};
-use splines::{Interpolation, Key, Spline};
+use super::error::ReconstructError;
+use super::interpolation::{Interpolant, Scheme};
+use crate::curve::{Frame, Point};
+use nalgebra::{Matrix3, Rotation3, Vector3};
 
 /// (distance, ka, kb)
 pub trait PointSlice {
-    fn interpolate(&self, ds: f64) -> CurvatureSplines;
+    /// Linear interpolate.
+    #[allow(dead_code)]
+    fn interpolate(&self, ds: f64) -> Result<CurvatureSplines, ReconstructError> {
+        self.interpolate_with(ds, Scheme::Linear)
+    }
+
+    fn interpolate_with(
+        &self,
+        ds: f64,
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError>;
+
+    /// Interpolate with fixed or adaptive steps.
+    fn interpolate_step(
+        &self,
+        step: Step,
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError>;
+
+    /// Interpolate at the start of each step, from the first station.
+    fn interpolate_steps(
+        &self,
+        steps: &[f64],
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError>;
 
     /// Set some error
+    #[allow(dead_code)]
     fn set_error(&self, index: usize, err: (f64, f64)) -> Vec<(f64, f64, f64)>;
 }
 
+#[derive(Clone)]
 pub struct CurvatureSplines {
-    // delta s
-    ds: f64,
-    pub splines: Vec<(f64, f64)>, // (ka, kb)
+    // delta s of each step
+    steps: Vec<f64>,
+    pub splines: Vec<(f64, f64)>, // (ka, kb) at the start of each step
+    // arc length of all steps
+    length: f64,
+}
+
+/// Steps of arc length between reconstructed points.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Step {
+    /// Fixed delta s.
+    Fixed(f64),
+
+    /// Delta s in [min, max], refined where curvature or its gradient is high,
+    /// so that the sagitta of each step and the tip error beyond the shortest step
+    /// stay under tolerance.
+    Adaptive { tolerance: f64, min: f64, max: f64 },
+}
+
+/// Integrator of Frenet-Serret equations.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Integrator {
+    /// Explicit Euler, projected onto SO(3) at every step.
+    Euler,
+
+    /// Classical fourth order Runge-Kutta.
+    RungeKutta4,
+
+    /// Fourth order Magnus expansion on the Lie group, exact for helices.
+    Magnus,
+}
+
+impl Step {
+    /// Adaptive steps from a fixed delta s, which becomes the shortest step,
+    /// so reconstructed points are never more than the fixed step.
+    pub fn adaptive(self, tolerance: f64) -> Self {
+        match self {
+            Step::Fixed(ds) | Step::Adaptive { min: ds, .. } => Step::Adaptive {
+                tolerance,
+                min: ds,
+                max: ds * ADAPTIVE_RANGE,
+            },
+        }
+    }
+
+    /// The longest delta s.
+    pub fn longest(self) -> f64 {
+        match self {
+            Step::Fixed(ds) | Step::Adaptive { max: ds, .. } => ds,
+        }
+    }
 }
 
 impl<T> PointSlice for T
 where
     T: AsRef<[(f64, f64, f64)]>,
 {
-    // Linear
-    fn interpolate(&self, ds: f64) -> CurvatureSplines {
+    fn interpolate_with(
+        &self,
+        ds: f64,
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError> {
         let data = self.as_ref();
-        if data.is_empty() {
-            panic!("data set cannot be empty")
+        validate(data)?;
+        if !(ds > 0. && ds.is_finite()) {
+            return Err(ReconstructError::InvalidStep(ds));
         }
-        let ka_keys = data
-            .iter()
-            .map(|(s, a, _)| Key::new(*s, *a, Interpolation::Linear));
-        let kb_keys = data
-            .iter()
-            .map(|(s, _, b)| Key::new(*s, *b, Interpolation::Linear));
-        let ka_splines = Spline::from_iter(ka_keys);
-        let kb_splines = Spline::from_iter(kb_keys);
-
-        let mut start = data[0].0;
-        let max = data[data.len() - 1].0;
+        let start = data[0].0;
+        let length = data[data.len() - 1].0 - start;
+        // a remnant within rounding error of ds is merged into the last step.
+        let count = ((length / ds) * (1. - 1e-9)).ceil().max(1.) as usize;
+        let mut steps = vec![ds; count];
+        // the last step is shortened to end at the last station.
+        steps[count - 1] = length - (count - 1) as f64 * ds;
+        // stations are computed by index, without accumulated error.
+        let stations = (0..count).map(|i| start + i as f64 * ds);
+        Ok(CurvatureSplines {
+            splines: sample(data, scheme, stations)?,
+            steps,
+            length,
+        })
+    }
 
-        let mut splines = Vec::new();
-        while start <= max {
-            splines.push((
+    fn interpolate_step(
+        &self,
+        step: Step,
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError> {
+        let (tolerance, min, max) = match step {
+            Step::Fixed(ds) => return self.interpolate_with(ds, scheme),
+            Step::Adaptive {
+                tolerance,
+                min,
+                max,
+            } => (tolerance, min, max),
+        };
+        let data = self.as_ref();
+        validate(data)?;
+        if !(tolerance > 0. && tolerance.is_finite()) {
+            return Err(ReconstructError::InvalidStep(tolerance));
+        }
+        if !(min > 0. && min.is_finite()) {
+            return Err(ReconstructError::InvalidStep(min));
+        }
+        if !(max >= min && max.is_finite()) {
+            return Err(ReconstructError::InvalidStep(max));
+        }
+        let ka_splines = Interpolant::new(data.iter().map(|(s, a, _)| (*s, *a)), scheme);
+        let kb_splines = Interpolant::new(data.iter().map(|(s, _, b)| (*s, *b)), scheme);
+        let sample = |s: f64| -> Result<Vector3<f64>, ReconstructError> {
+            Ok(Vector3::new(
                 ka_splines
-                    .sample(start)
-                    .expect(&format!("start: {}", start)),
+                    .sample(s)
+                    .ok_or(ReconstructError::OutOfRange(s))?,
                 kb_splines
-                    .sample(start)
-                    .expect(&format!("start: {}", start)),
-            ));
-            start += ds;
+                    .sample(s)
+                    .ok_or(ReconstructError::OutOfRange(s))?,
+                0.,
+            ))
+        };
+
+        let mut start = data[0].0;
+        let end = data[data.len() - 1].0;
+        let length = end - start;
+        let mut steps = Vec::new();
+        let mut splines = Vec::new();
+        while end - start > min * 1e-9 {
+            let k = sample(start)?;
+            let mut h = max.min(end - start);
+            for _ in 0..MAX_REFINEMENT {
+                let k_next = sample((start + h).min(end))?;
+                let curvature = k.norm().max(k_next.norm());
+                let gradient = (k_next - k).norm() / h;
+                // sagitta k * h^2 / 8 is under tolerance, and heading error g * h^2 / 2
+                // of constant curvature moves the tip by at most tolerance * h / length.
+                let bound = (8. * tolerance / curvature)
+                    .sqrt()
+                    .min(2. * tolerance / (gradient * length));
+                if h <= bound || h <= min {
+                    break;
+                }
+                h = bound.max(min);
+            }
+            // merge a remnant shorter than min into this step.
+            if end - start - h < min {
+                h = end - start;
+            }
+            steps.push(h);
+            splines.push((k.x, k.y));
+            start += h;
         }
+        Ok(CurvatureSplines {
+            steps,
+            splines,
+            length,
+        })
+    }
 
-        CurvatureSplines { ds, splines }
+    fn interpolate_steps(
+        &self,
+        steps: &[f64],
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError> {
+        let data = self.as_ref();
+        validate(data)?;
+        if let Some(ds) = steps.iter().find(|ds| !(**ds > 0. && ds.is_finite())) {
+            return Err(ReconstructError::InvalidStep(*ds));
+        }
+        let start = data[0].0;
+        // stations are computed by index over leading steps of the same ds, like `interpolate_with`,
+        // then accumulated over the others, e.g. adaptive steps.
+        let ds = steps.first().cloned().unwrap_or_default();
+        let uniform = steps.iter().take_while(|step| **step == ds).count();
+        let mut station = start + uniform as f64 * ds;
+        let stations =
+            (0..uniform)
+                .map(|i| start + i as f64 * ds)
+                .chain(steps[uniform..].iter().map(|step| {
+                    let current = station;
+                    station += step;
+                    current
+                }));
+        Ok(CurvatureSplines {
+            splines: sample(data, scheme, stations)?,
+            steps: steps.to_vec(),
+            length: steps.iter().sum(),
+        })
     }
 
     /// Set some error
@@ -63,130 +236,330 @@ where
     }
 }
 
+/// Interpolate (ka, kb) of data at stations.
+fn sample(
+    data: &[(f64, f64, f64)],
+    scheme: Scheme,
+    stations: impl Iterator<Item = f64>,
+) -> Result<Vec<(f64, f64)>, ReconstructError> {
+    let ka_splines = Interpolant::new(data.iter().map(|(s, a, _)| (*s, *a)), scheme);
+    let kb_splines = Interpolant::new(data.iter().map(|(s, _, b)| (*s, *b)), scheme);
+    stations
+        .map(|s| {
+            Ok((
+                ka_splines
+                    .sample(s)
+                    .ok_or(ReconstructError::OutOfRange(s))?,
+                kb_splines
+                    .sample(s)
+                    .ok_or(ReconstructError::OutOfRange(s))?,
+            ))
+        })
+        .collect()
+}
+
+/// Check stations are finite and sorted by arc length.
+pub fn validate(data: &[(f64, f64, f64)]) -> Result<(), ReconstructError> {
+    match data.len() {
+        0 => return Err(ReconstructError::Empty),
+        1 => {
+            return Err(ReconstructError::TooFewSamples {
+                required: 2,
+                found: 1,
+            })
+        }
+        _ => (),
+    }
+    for (index, (s, ka, kb)) in data.iter().enumerate() {
+        if !(s.is_finite() && ka.is_finite() && kb.is_finite()) {
+            return Err(ReconstructError::NonFinite { index });
+        }
+        if index > 0 && *s <= data[index - 1].0 {
+            return Err(ReconstructError::NonMonotonic { index, s: *s });
+        }
+    }
+    Ok(())
+}
+
+/// Ratio of the longest to the shortest adaptive step, from a fixed step.
+const ADAPTIVE_RANGE: f64 = 20.;
+
+/// Iterations of refining an adaptive step.
+const MAX_REFINEMENT: usize = 8;
+
+/// Steps between two re-orthonormalizations of the rotation.
+const RENORMALIZE_PERIOD: usize = 64;
+
+/// Composite curvature below which a step is treated as straight.
+const STRAIGHT_CURVATURE: f64 = 1e-12;
+
+/// Check rotation matrix is finite and invertible, then project it onto SO(3).
+pub fn validate_rotation(ri: &Matrix3<f64>) -> Result<Rotation3<f64>, ReconstructError> {
+    if ri.iter().all(|v| v.is_finite()) && ri.determinant() > 1e-9 {
+        let mut rotation = Rotation3::from_matrix_unchecked(*ri);
+        rotation.renormalize();
+        Ok(rotation)
+    } else {
+        Err(ReconstructError::SingularRotation)
+    }
+}
+
+/// Wrap angle into (-pi, pi].
+fn wrap_angle(angle: f64) -> f64 {
+    use std::f64::consts::PI;
+    let wrapped = (angle + PI).rem_euclid(2. * PI) - PI;
+    if wrapped == -PI {
+        PI
+    } else {
+        wrapped
+    }
+}
+
+/// Rotation about z axis.
+#[rustfmt::skip]
+fn rotation_z(cos: f64, sin: f64) -> Rotation3<f64> {
+    Rotation3::from_matrix_unchecked(Matrix3::new(
+        cos, -sin, 0.,
+        sin, cos, 0.,
+        0., 0., 1.,
+    ))
+}
+
+/// Rotation about y axis.
+#[rustfmt::skip]
+fn rotation_y(cos: f64, sin: f64) -> Rotation3<f64> {
+    Rotation3::from_matrix_unchecked(Matrix3::new(
+        cos, 0., sin,
+        0., 1., 0.,
+        -sin, 0., cos,
+    ))
+}
+
+/// Push absolute coordinate and frame of current point.
+pub fn push_point(points: &mut Vec<Point>, ai: &Vector3<f64>, ri: &Rotation3<f64>) {
+    points.push(Point {
+        x: ai[0] as f32,
+        y: ai[1] as f32,
+        z: ai[2] as f32,
+        frame: Some(Frame::from_matrix(&ri.inverse())),
+    });
+}
+
 impl CurvatureSplines {
+    /// Splines of the same delta s.
     #[allow(dead_code)]
-    #[rustfmt::skip]
-    pub fn curvature_reconstruct(&self, mut ai: Vector3<f64>, mut ri: Matrix3<f64>) -> Result<Vec<Point>, &'static str> {
+    pub fn uniform(ds: f64, splines: Vec<(f64, f64)>) -> Self {
+        Self {
+            steps: vec![ds; splines.len()],
+            length: ds * splines.len() as f64,
+            splines,
+        }
+    }
+
+    /// Straight splines of steps.
+    pub fn straight(steps: Vec<f64>) -> Self {
+        Self {
+            length: steps.iter().sum(),
+            splines: vec![(0., 0.); steps.len()],
+            steps,
+        }
+    }
+
+    /// Splines of these steps followed by those of `next`.
+    pub fn chain(mut self, next: Self) -> Self {
+        self.steps.extend(next.steps);
+        self.splines.extend(next.splines);
+        self.length += next.length;
+        self
+    }
+
+    /// Delta s of each step.
+    pub fn steps(&self) -> &[f64] {
+        &self.steps
+    }
+
+    /// Arc length from the first station to the last reconstructed point.
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    pub fn curvature_reconstruct(
+        &self,
+        mut ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        // ri maps absolute coordinate to relative coordinate, so its inverse is its transpose.
+        let mut ri = validate_rotation(&ri)?;
         let mut points = Vec::with_capacity(self.splines.len()); // define points vector and reserve capacity
-        for (ka, kb) in self.splines.iter().cloned() {
-            if ka == 0. && kb == 0. {
+        for (step, ((ka, kb), ds)) in self.splines.iter().cloned().zip(&self.steps).enumerate() {
+            let k = (ka.powi(2) + kb.powi(2)).sqrt(); // composite curvature
+            if k < STRAIGHT_CURVATURE {
                 // ka == kb == 0, no rotation, only translation.
-                // Ti vector, a translation vector.
-                let ti = ri.pseudo_inverse(0.000000001)? * Vector3::new(0., 0., self.ds);
-
-                // ai + ti, to get absolute coordinate of current point
-                ai += ti;
-                let slice = ai.column(0);
-                // push absolute coordinate of current point
-                // println!("(x, y, z): ({}, {}, {})", slice[0], slice[1], slice[2]);
-                points.push(Point {
-                    x: slice[0] as f32,
-                    y: slice[1] as f32,
-                    z: slice[2] as f32,
-                });
+                ai += ri.inverse_transform_vector(&Vector3::new(0., 0., *ds));
+                push_point(&mut points, &ai, &ri);
             } else {
-                let k = (ka.powi(2) + kb.powi(2)).sqrt(); // composite curvature
-                let theta = k * self.ds;
+                let theta = k * ds;
                 let cos_alpha = ka / k;
                 let sin_alpha = kb / k;
                 let cos_theta = theta.cos();
                 let sin_theta = theta.sin();
-                // relative coordinate (da, db, dc)
-                let da = cos_alpha * (1. - cos_theta) / k;
-                let db = sin_alpha * (1. - cos_theta) / k;
+                // relative coordinate (da, db, dc); the curve bends toward -(ka, kb).
+                let da = -cos_alpha * (1. - cos_theta) / k;
+                let db = -sin_alpha * (1. - cos_theta) / k;
                 let dc = sin_theta / k;
 
-                // get generalized inverse of ti; then dot product relative coordinate
-                let ti = ri.pseudo_inverse(0.000000001)? * Vector3::new(da, db, dc);
                 // ai + ti, to get absolute coordinate of current point
-                ai += ti;
-                let slice = ai.column(0);
-                // push absolute coordinate of current point
-                // println!("(x, y, z): ({}, {}, {})", slice[0], slice[1], slice[2]);
-                points.push(Point {
-                    x: slice[0] as f32,
-                    y: slice[1] as f32,
-                    z: slice[2] as f32,
-                });
+                ai += ri.inverse_transform_vector(&Vector3::new(da, db, dc));
 
                 // get next rotation matrix
-                ri = Matrix3::new(
-                    cos_alpha, -sin_alpha, 0.,
-                    sin_alpha, cos_alpha, 0.,
-                    0., 0., 1.,
-                ) * Matrix3::new(
-                    cos_theta, 0., sin_theta,
-                    0., 1., 0.,
-                    -sin_theta, 0., cos_theta,
-                ) * Matrix3::new(
-                    cos_alpha, sin_alpha, 0.,
-                    -sin_alpha, cos_alpha, 0.,
-                    0., 0., 1.,
-                ) * ri;
+                ri = rotation_z(cos_alpha, sin_alpha)
+                    * rotation_y(cos_theta, sin_theta)
+                    * rotation_z(cos_alpha, -sin_alpha)
+                    * ri;
+                push_point(&mut points, &ai, &ri);
+            }
+            if step % RENORMALIZE_PERIOD == RENORMALIZE_PERIOD - 1 {
+                ri.renormalize();
             }
         }
-        // println!("cost {}ms", time.elapsed().as_millis());
         Ok(points)
     }
 
-    #[rustfmt::skip]
-    pub fn frenet_reconstruct(&self, mut ai: Vector3<f64>, mut ri: Matrix3<f64>) -> Result<Vec<Point>, &'static str> {
+    pub fn frenet_reconstruct(
+        &self,
+        mut ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        // ri maps absolute coordinate to relative coordinate, so its inverse is its transpose.
+        let mut ri = validate_rotation(&ri)?;
         let mut points = Vec::with_capacity(self.splines.len()); // define points vector and reserve capacity
-        let mut alpha_last = 0.;
-        for (ka, kb) in self.splines.iter().cloned() {
-            if ka == 0. && kb == 0. {
-                // ka == kb == 0, no rotation, only translation.
 
-                // Ti vector, a translation vector.
-                let ti = ri.pseudo_inverse(0.000000001)? * Vector3::new(0., 0., self.ds);
-
-                // ai + ti, to get absolute coordinate of current point
-                ai += ti;
-                let slice = ai.column(0);
-                // push absolute coordinate of current point
-                // println!("(x, y, z): ({}, {}, {})", slice[0], slice[1], slice[2]);
-                points.push(Point {
-                    x: slice[0] as f32,
-                    y: slice[1] as f32,
-                    z: slice[2] as f32,
-                });
+        // unwrapped roll of ri around tangent, relative to the sensor frame.
+        // it is kept on straight steps, so the frame stays continuous through them.
+        let mut alpha_last: f64 = 0.;
+        let (mut alpha_cos, mut alpha_sin) = (1., 0.);
+        for (step, ((ka, kb), ds)) in self.splines.iter().cloned().zip(&self.steps).enumerate() {
+            let k = (ka.powi(2) + kb.powi(2)).sqrt(); // composite curvature
+            if k < STRAIGHT_CURVATURE {
+                // ka == kb == 0, no rotation, only translation.
+                ai += ri.inverse_transform_vector(&Vector3::new(0., 0., *ds));
+                push_point(&mut points, &ai, &(rotation_z(alpha_cos, alpha_sin) * ri));
             } else {
-                let k = (ka.powi(2) + kb.powi(2)).sqrt(); // composite curvature
-                let theta = k * self.ds;
-                let alpha = (ka / k).acos();
-                let phi = alpha - alpha_last;
-                alpha_last = alpha;
+                let theta = k * ds;
+                // bending direction in the sensor frame, in (-pi, pi].
+                let alpha = kb.atan2(ka);
+                let phi = wrap_angle(alpha - alpha_last);
+                alpha_last += phi;
 
-                let cos_phi = phi.cos();
-                let sin_phi = phi.sin();
                 let cos_theta = theta.cos();
                 let sin_theta = theta.sin();
 
+                // roll x axis of ri to the bending direction.
+                ri = rotation_z(phi.cos(), -phi.sin()) * ri;
 
-                ri = Matrix3::new(
-                    cos_phi, -sin_phi, 0.,
-                    sin_phi, cos_phi, 0.,
-                    0., 0., 1.,
-                ) * ri;
+                // ai + ti, to get absolute coordinate of current point
+                ai += ri.inverse_transform_vector(&Vector3::new(
+                    -(1. - cos_theta) / k,
+                    0.,
+                    sin_theta / k,
+                ));
 
-                // get generalized inverse of ri; then dot product relative coordinate
-                let ti = ri.pseudo_inverse(0.000000001)? * Vector3::new((1. - cos_theta) / k, 0., sin_theta / k);
-                ai += ti;
-                let slice = ai.column(0);
-                // push absolute coordinate of current point
-                // println!("(x, y, z): ({}, {}, {})", slice[0], slice[1], slice[2]);
-                points.push(Point {
-                    x: slice[0] as f32,
-                    y: slice[1] as f32,
-                    z: slice[2] as f32,
-                });
+                ri = rotation_y(cos_theta, sin_theta) * ri;
+                alpha_cos = alpha_last.cos();
+                alpha_sin = alpha_last.sin();
+                // roll back to the sensor frame.
+                push_point(&mut points, &ai, &(rotation_z(alpha_cos, alpha_sin) * ri));
+            }
+            if step % RENORMALIZE_PERIOD == RENORMALIZE_PERIOD - 1 {
+                ri.renormalize();
+            }
+        }
+        Ok(points)
+    }
 
-                ri = Matrix3::new(
-                    cos_theta, 0., sin_theta,
-                    0., 1., 0.,
-                    -sin_theta, 0., cos_theta,
-                ) * ri;
+    /// Reconstruct by Frenet-Serret equations, taking samples as (curvature, torsion).
+    ///
+    /// Tangent of ri is z axis, and the curve bends toward -x axis,
+    /// the same as other reconstructors.
+    pub fn torsion_reconstruct(
+        &self,
+        mut ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+        integrator: Integrator,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        let mut ri = validate_rotation(&ri)?;
+        let mut points = Vec::with_capacity(self.splines.len()); // define points vector and reserve capacity
+        let tangent = Vector3::z();
+        for (step, ((kappa, tau), h)) in self
+            .splines
+            .iter()
+            .cloned()
+            .zip(self.steps.iter().cloned())
+            .enumerate()
+        {
+            // Darboux vector in relative coordinate, at both ends of this step.
+            let (kappa_next, tau_next) =
+                self.splines.get(step + 1).cloned().unwrap_or((kappa, tau));
+            let omega = Vector3::new(0., -kappa, tau);
+            let omega_next = Vector3::new(0., -kappa_next, tau_next);
+            let omega_at = |c: f64| omega * (1. - c) + omega_next * c;
+
+            match integrator {
+                Integrator::Euler => {
+                    ai += ri.inverse_transform_vector(&(tangent * h));
+                    let next = (Matrix3::identity() - omega.cross_matrix() * h) * ri.matrix();
+                    ri = Rotation3::from_matrix_unchecked(next);
+                    ri.renormalize();
+                }
+                Integrator::RungeKutta4 => {
+                    // d(ri)/ds = -[omega]x * ri, d(ai)/ds = ri^T * z
+                    let derivative = |c: f64, r: &Matrix3<f64>| -omega_at(c).cross_matrix() * r;
+                    let r1 = *ri.matrix();
+                    let k1 = derivative(0., &r1);
+                    let r2 = r1 + k1 * (h / 2.);
+                    let k2 = derivative(0.5, &r2);
+                    let r3 = r1 + k2 * (h / 2.);
+                    let k3 = derivative(0.5, &r3);
+                    let r4 = r1 + k3 * h;
+                    let k4 = derivative(1., &r4);
+                    let velocity = (r1.transpose()
+                        + r2.transpose() * 2.
+                        + r3.transpose() * 2.
+                        + r4.transpose())
+                        * tangent;
+                    ai += velocity * (h / 6.);
+                    ri = Rotation3::from_matrix_unchecked(
+                        r1 + (k1 + k2 * 2. + k3 * 2. + k4) * (h / 6.),
+                    );
+                    if step % RENORMALIZE_PERIOD == RENORMALIZE_PERIOD - 1 {
+                        ri.renormalize();
+                    }
+                }
+                Integrator::Magnus => {
+                    // fourth order Magnus expansion at two Gauss points.
+                    let offset = 3f64.sqrt() / 6.;
+                    let (omega1, omega2) = (omega_at(0.5 - offset), omega_at(0.5 + offset));
+                    let phi = (omega1 + omega2) * (h / 2.)
+                        - omega2.cross(&omega1) * (3f64.sqrt() / 12. * h * h);
+
+                    // exact displacement on SE(3) for a constant Darboux vector.
+                    let theta = phi.norm();
+                    let skew = phi.cross_matrix();
+                    let jacobian = if theta < STRAIGHT_CURVATURE {
+                        Matrix3::identity() + skew / 2.
+                    } else {
+                        Matrix3::identity()
+                            + skew * ((1. - theta.cos()) / theta.powi(2))
+                            + skew * skew * ((theta - theta.sin()) / theta.powi(3))
+                    };
+                    ai += ri.inverse_transform_vector(&(jacobian * tangent * h));
+                    ri = Rotation3::from_scaled_axis(-phi) * ri;
+                    if step % RENORMALIZE_PERIOD == RENORMALIZE_PERIOD - 1 {
+                        ri.renormalize();
+                    }
+                }
             }
+            push_point(&mut points, &ai, &ri);
         }
         Ok(points)
     }
@@ -194,7 +567,11 @@ impl CurvatureSplines {
 
 #[cfg(test)]
 mod tests {
-    use super::PointSlice;
+    use super::{CurvatureSplines, Integrator, PointSlice, Step};
+    use crate::channels::error::ReconstructError;
+    use crate::channels::interpolation::Scheme;
+    use crate::channels::metrics;
+    use crate::curve::Point;
     use nalgebra::{Matrix3, Vector3};
     use num::{One, Zero};
     use plotlib::page::Page;
@@ -220,8 +597,8 @@ mod tests {
     ];
 
     #[test]
-    fn reconstruct() -> Result<(), &'static str> {
-        let data = DATA.interpolate(0.1);
+    fn reconstruct() -> Result<(), ReconstructError> {
+        let data = DATA.interpolate(0.1)?;
         let curvature = data.curvature_reconstruct(Zero::zero(), One::one())?;
         let frenet = data.frenet_reconstruct(Zero::zero(), One::one())?;
         for i in 0..curvature.len() {
@@ -230,19 +607,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn reject_bad_frames() {
+        let empty: [(f64, f64, f64); 0] = [];
+        assert_eq!(Some(ReconstructError::Empty), empty.interpolate(0.1).err());
+        assert_eq!(
+            Some(ReconstructError::TooFewSamples {
+                required: 2,
+                found: 1
+            }),
+            [(0., 0.1, 0.)].interpolate(0.1).err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonMonotonic { index: 2, s: 4. }),
+            [(0., 0., 0.), (5., 0.1, 0.), (4., 0.2, 0.)]
+                .interpolate(0.1)
+                .err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonFinite { index: 1 }),
+            [(0., 0., 0.), (5., f64::NAN, 0.), (10., 0.2, 0.)]
+                .interpolate(0.1)
+                .err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonFinite { index: 2 }),
+            [(0., 0., 0.), (5., 0.1, 0.), (10., 0.2, f64::INFINITY)]
+                .interpolate(0.1)
+                .err()
+        );
+        assert_eq!(
+            Some(ReconstructError::InvalidStep(0.)),
+            DATA.interpolate(0.).err()
+        );
+        let splines = DATA.interpolate(0.1).unwrap();
+        assert_eq!(
+            Some(ReconstructError::SingularRotation),
+            splines
+                .frenet_reconstruct(Zero::zero(), Matrix3::zeros())
+                .err()
+        );
+        assert_eq!(
+            Some(ReconstructError::SingularRotation),
+            splines
+                .curvature_reconstruct(Zero::zero(), Matrix3::from_element(f64::NAN))
+                .err()
+        );
+    }
+
     #[test]
     fn interpolate() {
-        // if let Some(splines) = DATA.interpolate(0.2) {
-        //     println!("ds({}):", splines.ds);
-        //     for (da, db) in splines.splines {
-        //         println!("({}, {}):", da, db);
-        //     }
-        // }
+        // linear interpolation agrees with `splines`.
         let splines = Spline::from_iter(
             DATA.iter()
-                .map(|(s, a, _)| Key::new(*s, *a, Interpolation::CatmullRom)),
+                .map(|(s, a, _)| Key::new(*s, *a, Interpolation::Linear)),
         );
-        println!("{:?}", splines.sample(5.));
+        let linear = DATA.interpolate(0.2).unwrap();
+        for (i, (ka, _)) in linear.splines.iter().enumerate() {
+            let sample = splines.sample(i as f64 * 0.2).unwrap();
+            assert!((ka - sample).abs() < 1e-9);
+        }
+
+        // smooth schemes are usable in production path, and cover the same stations.
+        for scheme in [
+            Scheme::CatmullRom,
+            Scheme::CubicHermite,
+            Scheme::NaturalCubic,
+            Scheme::Akima,
+            Scheme::Monotone,
+        ]
+        .iter()
+        {
+            let splines = DATA.interpolate_with(0.2, *scheme).unwrap();
+            assert_eq!(linear.splines.len(), splines.splines.len());
+        }
+    }
+
+    #[test]
+    fn cover_range() {
+        for (end, ds, count) in [
+            (29.95, 0.05, 599),
+            (29.95, 0.1, 300),
+            (1., 0.3, 4),
+            (0.1, 0.3, 1),
+            (3., 0.1, 30),
+        ]
+        .iter()
+        {
+            let data = [(0., 0.1, 0.), (*end, 0.2, 0.)];
+            let splines = data.interpolate(*ds).unwrap();
+            assert_eq!(*count, splines.splines.len(), "{} / {}", end, ds);
+            assert_eq!(*end, splines.length());
+            let steps = splines.steps();
+            assert!(steps[..count - 1].iter().all(|step| step == ds));
+            let last = steps[count - 1];
+            assert!(last > 0. && last <= ds * (1. + 1e-9), "{} / {}", end, ds);
+            // stations by index, the last one is within the last step.
+            let station = (count - 1) as f64 * ds;
+            let ka = 0.1 + 0.1 * station / end;
+            assert!((splines.splines[count - 1].0 - ka).abs() < 1e-12);
+
+            // the tip reaches the end of a straight rod.
+            let points = [(0., 0., 0.), (*end, 0., 0.)]
+                .interpolate(*ds)
+                .unwrap()
+                .frenet_reconstruct(Zero::zero(), One::one())
+                .unwrap();
+            let tip = points.last().unwrap();
+            assert!((tip.z as f64 - end).abs() < 1e-5, "{:?}", tip);
+        }
     }
 
     #[test]
@@ -289,6 +762,7 @@ mod tests {
         // We can plot multiple data sets in the same view
         let data2 = DATA
             .interpolate(0.1)
+            .unwrap()
             .frenet_reconstruct(Zero::zero(), One::one())
             .unwrap()
             .into_iter()
@@ -327,30 +801,46 @@ mod tests {
             .unwrap()
     }
 
-    fn error(data: &[(f64, f64)], std_fn: impl Fn(&f64) -> f64) -> Vec<(f64, f64)> {
-        let mut errors = data.to_owned();
-        for (x, y) in errors.iter_mut() {
-            *y = (*y - std_fn(x)).abs()
-        }
-        errors
+    /// Planar points (x, y) reconstructed from (0, 1), as a polyline from the base.
+    fn polyline(data: &[(f64, f64)]) -> Vec<Vector3<f64>> {
+        let mut line = vec![Vector3::new(0., 0., 1.)];
+        line.extend(data.iter().map(|(x, y)| Vector3::new(-x, 0., *y)));
+        line
     }
 
-    fn relative_error(data: &[(f64, f64)], std_fn: impl Fn(&f64) -> f64) -> Vec<(f64, f64)> {
-        let mut errors = data.to_owned();
-        for (x, y) in errors.iter_mut() {
-            let s = cos_s(*x);
-            if s == 0. {
-                *y = 0.
-            } else {
-                *y = (*y - std_fn(x)).abs() / s
-            }
-        }
+    /// Polyline of y = cos(x) from 0 to 2π, in the plane of `polyline`.
+    fn cos_reference() -> Vec<Vector3<f64>> {
+        (0..=2000)
+            .map(|i| i as f64 * 2. * PI / 2000.)
+            .map(|x| Vector3::new(-x, 0., x.cos()))
+            .collect()
+    }
+
+    /// Errors of planar points against y = cos(x), by arc length.
+    fn cos_errors(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        metrics::errors(&polyline(data), &cos_reference()).unwrap()
+    }
+
+    /// Errors of planar points against the unit circle from (0, 1), by arc length.
+    fn circle_errors(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let reference = (0..=2000)
+            .map(|i| i as f64 * 2. * PI / 2000.)
+            .map(|theta| Vector3::new(-theta.sin(), 0., theta.cos()))
+            .collect::<Vec<_>>();
+        metrics::errors(&polyline(data), &reference).unwrap()
+    }
+
+    /// Errors divided by arc length.
+    fn relative(errors: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
         errors
+            .into_iter()
+            .map(|(s, error)| (s, if s == 0. { 0. } else { error / s }))
+            .collect()
     }
 
     fn cos_error(data: impl AsRef<[(f64, f64)]>, legend: impl Into<String>) -> Plot {
         let mut rng = SmallRng::from_entropy();
-        Plot::new(error(data.as_ref(), |x| x.cos()))
+        Plot::new(cos_errors(data.as_ref()))
             .legend(legend.into())
             .line_style(LineStyle::new().colour(Colour::random(&mut rng)))
     }
@@ -378,6 +868,7 @@ mod tests {
             .map(|x| (cos_s(x), cos_curvature(x), 0.)) // get pair (<arc length>, <curvature>, 0.)
             .collect::<Vec<_>>()
             .interpolate(0.01) // linear interpolate; ds = 0.01.
+            .unwrap()
             .frenet_reconstruct(
                 Vector3::new(0., 0., 1.),                          // initialized coordinate
                 Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -387,13 +878,13 @@ mod tests {
             .map(|point| (-point.x as f64, point.z as f64))
             .collect();
         let mut csv_file = Writer::from_path("cos.csv").unwrap();
-        csv_file.write_record(&["x", "y"]).unwrap();
+        csv_file.write_record(["x", "y"]).unwrap();
         for (x, y) in data2.iter() {
             csv_file.serialize((*x, *y)).unwrap();
         }
 
         let s_error = cos_error(&data2, "Reconstructed Curve");
-        let s_relative_error = Plot::new(relative_error(&data2, |x| x.cos()))
+        let s_relative_error = Plot::new(relative(cos_errors(&data2)))
             .legend("Reconstructed Curve".into())
             .line_style(LineStyle::new().colour(Colour::random(&mut rng)));
 
@@ -412,16 +903,16 @@ mod tests {
 
         let v_error = ContinuousView::new()
             .add(s_error)
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.1)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         let v_relative_error = ContinuousView::new()
             .add(s_relative_error)
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.05)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         // A page with a single view is then saved to an SVG file
@@ -527,6 +1018,7 @@ mod tests {
         for (i, step) in [0.1, 0.01, 0.001].iter().enumerate() {
             let data: Vec<_> = raw_data
                 .interpolate(*step) // linear interpolate
+                .unwrap()
                 .frenet_reconstruct(
                     Vector3::new(0., 0., 1.),                          // initialized coordinate
                     Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -538,12 +1030,12 @@ mod tests {
             let color = colors[i];
 
             error_view = error_view.add(
-                Plot::new(error(&data, |x| x.cos()))
+                Plot::new(cos_errors(&data))
                     .legend(format!("step {:.3}", *step))
                     .line_style(LineStyle::new().colour(color)),
             );
             relative_error_view = relative_error_view.add(
-                Plot::new(relative_error(&data, |x| x.cos()))
+                Plot::new(relative(cos_errors(&data)))
                     .legend(format!("step {:.3}", *step))
                     .line_style(LineStyle::new().colour(color)),
             );
@@ -555,15 +1047,15 @@ mod tests {
         }
 
         let error_v = error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.5)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         let relative_error_v = relative_error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.1)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         // The 'view' describes what set of data is drawn
@@ -594,7 +1086,8 @@ mod tests {
             .map(|i| i as f64 * 2. * PI / 8.) // get x
             .map(|x| (cos_s(x), cos_curvature(x), 0.)) // get pair (<arc length>, <curvature>, 0.)
             .collect::<Vec<_>>()
-            .interpolate(0.01);
+            .interpolate(0.01)
+            .unwrap();
 
         let frenet_data = splines
             .frenet_reconstruct(
@@ -616,40 +1109,48 @@ mod tests {
             .map(|point| (-point.x as f64, point.z as f64))
             .collect::<Vec<_>>();
 
+        // from nine stations, both methods follow the cos curve within linear interpolation.
+        for data in [&curvature_data, &frenet_data].iter() {
+            let metrics = metrics::evaluate(&polyline(data), &cos_reference()).unwrap();
+            assert!(metrics.max_error < 6e-2, "{:?}", metrics);
+            assert!(metrics.tip_angle_error < 1e-2, "{:?}", metrics);
+            assert!(metrics.length_drift.abs() < 1e-3, "{:?}", metrics);
+        }
+
         error_view = error_view.add(
-            Plot::new(error(&curvature_data, |x| x.cos()))
+            Plot::new(cos_errors(&curvature_data))
                 .legend("Legacy Algorithm".to_string())
                 .line_style(LineStyle::new().colour("#FF0000")),
         );
 
         error_view = error_view.add(
-            Plot::new(error(&frenet_data, |x| x.cos()))
+            Plot::new(cos_errors(&frenet_data))
                 .legend("New Algorithm".to_string())
                 .line_style(LineStyle::new().colour("#0000FF")),
         );
 
         relative_error_view = relative_error_view.add(
-            Plot::new(relative_error(&curvature_data, |x| x.cos()))
+            Plot::new(relative(cos_errors(&curvature_data)))
                 .legend("Legacy Algorithm".to_string())
                 .line_style(LineStyle::new().colour("#FF0000")),
         );
 
         relative_error_view = relative_error_view.add(
-            Plot::new(relative_error(&frenet_data, |x| x.cos()))
+            Plot::new(relative(cos_errors(&frenet_data)))
                 .legend("New Algorithm".to_string())
                 .line_style(LineStyle::new().colour("#0000FF")),
         );
 
         let error_v = error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.1)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         let relative_error_v = relative_error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(0., 0.05)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         // A page with a single view is then saved to an SVG file
@@ -684,7 +1185,8 @@ mod tests {
             .map(|i| i as f64 * 2. * PI / 8.) // get x
             .map(|x| (cos_s(x), cos_curvature(x), 0.)) // get pair (<arc length>, <curvature>, 0.)
             .collect::<Vec<_>>()
-            .interpolate(0.01);
+            .interpolate(0.01)
+            .unwrap();
 
         let curvature_times = (1..1000)
             .map(|_| {
@@ -744,6 +1246,317 @@ mod tests {
             .unwrap();
     }
 
+    /// Signed Frenet reconstruction computing generalized inverse of rotation matrix at every step,
+    /// the reference of propagation on SO(3). Its cost is compared by `benches/reconstruct.rs`.
+    #[rustfmt::skip]
+    fn legacy_frenet_reconstruct(splines: &CurvatureSplines, mut ai: Vector3<f64>, mut ri: Matrix3<f64>) -> Vec<Point> {
+        let mut points = Vec::with_capacity(splines.splines.len());
+        let mut alpha_last = 0.;
+        for ((ka, kb), ds) in splines.splines.iter().cloned().zip(splines.steps().iter().cloned()) {
+            if ka == 0. && kb == 0. {
+                ai += ri.pseudo_inverse(0.000000001).unwrap() * Vector3::new(0., 0., ds);
+            } else {
+                let k = (ka.powi(2) + kb.powi(2)).sqrt();
+                let theta = k * ds;
+                let alpha = kb.atan2(ka);
+                let phi = alpha - alpha_last;
+                alpha_last = alpha;
+                ri = Matrix3::new(
+                    phi.cos(), phi.sin(), 0.,
+                    -phi.sin(), phi.cos(), 0.,
+                    0., 0., 1.,
+                ) * ri;
+                ai += ri.pseudo_inverse(0.000000001).unwrap() * Vector3::new(-(1. - theta.cos()) / k, 0., theta.sin() / k);
+                ri = Matrix3::new(
+                    theta.cos(), 0., theta.sin(),
+                    0., 1., 0.,
+                    -theta.sin(), 0., theta.cos(),
+                ) * ri;
+            }
+            points.push(Point { x: ai[0] as f32, y: ai[1] as f32, z: ai[2] as f32, frame: None });
+        }
+        points
+    }
+
+    #[test]
+    fn rotation_propagation() {
+        let splines = (0..9) // nine sample points.
+            .map(|i| i as f64 * 2. * PI / 8.) // get x
+            .map(|x| (cos_s(x), cos_curvature(x), cos_curvature(x) / 2.))
+            .collect::<Vec<_>>()
+            .interpolate(0.01)
+            .unwrap();
+        let ai = Vector3::new(0., 0., 1.);
+        let ri = Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.);
+
+        // same curve as the pseudo-inverse method.
+        let legacy = legacy_frenet_reconstruct(&splines, ai, ri);
+        let frenet = splines.frenet_reconstruct(ai, ri).unwrap();
+        assert_eq!(legacy.len(), frenet.len());
+        for (p, q) in legacy.iter().zip(frenet.iter()) {
+            assert!(
+                (p.x - q.x).abs() < 1e-4 && (p.y - q.y).abs() < 1e-4 && (p.z - q.z).abs() < 1e-4
+            );
+        }
+    }
+
+    /// Both reconstructors from the identity pose.
+    fn reconstruct_both(splines: &CurvatureSplines) -> (Vec<Point>, Vec<Point>) {
+        (
+            splines
+                .curvature_reconstruct(Zero::zero(), One::one())
+                .unwrap(),
+            splines
+                .frenet_reconstruct(Zero::zero(), One::one())
+                .unwrap(),
+        )
+    }
+
+    fn distance(p: &Point, q: &Point) -> f64 {
+        (((p.x - q.x).powi(2) + (p.y - q.y).powi(2) + (p.z - q.z).powi(2)) as f64).sqrt()
+    }
+
+    #[test]
+    fn bend_into_four_quadrants() {
+        let ds = 0.01;
+        for (ka, kb) in [(0.5, 0.3), (-0.5, 0.3), (-0.5, -0.3), (0.5, -0.3)].iter() {
+            let splines = CurvatureSplines::uniform(ds, vec![(*ka, *kb); 300]);
+            let k: f64 = (ka * ka + kb * kb).sqrt();
+            let (curvature, frenet) = reconstruct_both(&splines);
+            for (i, (p, q)) in curvature.iter().zip(frenet.iter()).enumerate() {
+                // exact arc, bending toward -(ka, kb).
+                let s = (i + 1) as f64 * ds;
+                let bend = -(1. - (k * s).cos()) / k;
+                let exact = Point {
+                    x: (bend * ka / k) as f32,
+                    y: (bend * kb / k) as f32,
+                    z: ((k * s).sin() / k) as f32,
+                    frame: None,
+                };
+                assert!(distance(p, &exact) < 1e-5, "({}, {}): {:?}", ka, kb, p);
+                assert!(distance(q, &exact) < 1e-5, "({}, {}): {:?}", ka, kb, q);
+            }
+        }
+    }
+
+    #[test]
+    fn helix() {
+        // a helix has curvature vector rotating at rate of torsion in a twist-free frame.
+        let (kappa, tau, ds) = (0.5f64, 0.2f64, 0.01);
+        let splines = CurvatureSplines::uniform(
+            ds,
+            (0..2000)
+                .map(|i| (i as f64 + 0.5) * ds)
+                .map(|s| (kappa * (tau * s).cos(), kappa * (tau * s).sin()))
+                .collect(),
+        );
+        let omega = (kappa.powi(2) + tau.powi(2)).sqrt();
+        let radius = kappa / omega.powi(2);
+        let pitch = tau / omega;
+        let (curvature, frenet) = reconstruct_both(&splines);
+        for (j, (p, q)) in curvature.iter().zip(frenet.iter()).enumerate() {
+            assert!(distance(p, q) < 1e-4, "{:?} != {:?}", p, q);
+            let arc = j as f64 * ds;
+            let chord =
+                (2. * radius.powi(2) * (1. - (omega * arc).cos()) + (pitch * arc).powi(2)).sqrt();
+            assert!((distance(&frenet[0], q) - chord).abs() < 1e-3, "{}", j);
+        }
+    }
+
+    #[test]
+    fn straight_section_continuity() {
+        // bend, go straight, then bend again in the same direction of the third quadrant.
+        let data = [
+            (0., -0.3, -0.4),
+            (2., -0.3, -0.4),
+            (2.5, 0., 0.),
+            (5., 0., 0.),
+            (5.5, -0.3, -0.4),
+            (8., -0.3, -0.4),
+        ];
+        let splines = data.interpolate(0.01).unwrap();
+        let (curvature, frenet) = reconstruct_both(&splines);
+        for (p, q) in curvature.iter().zip(frenet.iter()) {
+            assert!(distance(p, q) < 1e-4, "{:?} != {:?}", p, q);
+            // the curve stays in the bending plane.
+            assert!((p.x * 0.4 - p.y * 0.3).abs() < 1e-4, "{:?}", p);
+            assert!((q.x * 0.4 - q.y * 0.3).abs() < 1e-4, "{:?}", q);
+        }
+    }
+
+    #[test]
+    fn torsion_helix() {
+        // analytic helix starts from tangent z, normal -x and binormal -y.
+        let (kappa, tau, ds) = (0.5f64, 0.2f64, 0.01);
+        let helix = metrics::helix(kappa, tau);
+
+        let splines = [(0., kappa, tau), (20., kappa, tau)]
+            .interpolate(ds)
+            .unwrap();
+        for (integrator, tolerance) in [
+            (Integrator::Euler, 2e-2),
+            (Integrator::RungeKutta4, 1e-5),
+            (Integrator::Magnus, 1e-5),
+        ]
+        .iter()
+        {
+            let points = splines
+                .torsion_reconstruct(Zero::zero(), One::one(), *integrator)
+                .unwrap();
+            let max_error = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let exact = helix((i + 1) as f64 * ds);
+                    (Vector3::new(p.x as f64, p.y as f64, p.z as f64) - exact).norm()
+                })
+                .fold(0., f64::max);
+            assert!(max_error < *tolerance, "{:?}: {}", integrator, max_error);
+        }
+    }
+
+    #[test]
+    fn torsion_free_matches_frenet() {
+        // without torsion, curvature is ka and the curve is planar.
+        let splines = DATA.interpolate(0.01).unwrap();
+        let frenet = splines
+            .frenet_reconstruct(Zero::zero(), One::one())
+            .unwrap();
+        for integrator in [
+            Integrator::Euler,
+            Integrator::RungeKutta4,
+            Integrator::Magnus,
+        ]
+        .iter()
+        {
+            let torsion = splines
+                .torsion_reconstruct(Zero::zero(), One::one(), *integrator)
+                .unwrap();
+            let max_error = frenet
+                .iter()
+                .zip(torsion.iter())
+                .map(|(p, q)| distance(p, q))
+                .fold(0., f64::max);
+            assert!(max_error < 2e-2, "{:?}: {}", integrator, max_error);
+        }
+    }
+
+    #[test]
+    fn frames() {
+        let splines = [
+            (0., 0.2, 0.1),
+            (5., -0.1, 0.3),
+            (10., 0., 0.),
+            (15., 0.3, -0.2),
+        ]
+        .interpolate(0.01)
+        .unwrap();
+        let (curvature, frenet) = reconstruct_both(&splines);
+        let position = |p: &Point| Vector3::new(p.x as f64, p.y as f64, p.z as f64);
+        for points in [&curvature, &frenet].iter() {
+            for window in points.windows(2) {
+                // tangent is the direction of the next chord.
+                let rotation = window[0].frame.unwrap().rotation();
+                let tangent = rotation * Vector3::z();
+                let chord = (position(&window[1]) - position(&window[0])).normalize();
+                let tangent = Vector3::new(tangent.x as f64, tangent.y as f64, tangent.z as f64);
+                assert!((tangent - chord).norm() < 1e-2, "{:?}", window);
+            }
+        }
+
+        // frenet frames are rolled back to the sensor frame.
+        for (p, q) in curvature.iter().zip(frenet.iter()) {
+            let (p, q) = (p.frame.unwrap().rotation(), q.frame.unwrap().rotation());
+            assert!(p.angle_to(&q) < 1e-3, "{:?} {:?}", p, q);
+        }
+    }
+
+    #[test]
+    fn adaptive_step() {
+        // bend, go straight, then bend with varying curvature.
+        let data = [
+            (0., 0.3, 0.),
+            (2., 0.3, 0.),
+            (2.5, 0., 0.),
+            (18., 0., 0.),
+            (20., 0.1, 0.2),
+            (22., -0.4, 0.1),
+        ];
+        let positions = |splines: &CurvatureSplines| {
+            let mut points = vec![Vector3::zeros()];
+            points.extend(metrics::positions(
+                &splines
+                    .frenet_reconstruct(Zero::zero(), One::one())
+                    .unwrap(),
+            ));
+            points
+        };
+        let reference = positions(&data.interpolate(0.005).unwrap());
+        let fixed = data.interpolate(0.05).unwrap();
+        let fixed_error = metrics::evaluate(&positions(&fixed), &reference)
+            .unwrap()
+            .tip_error;
+        for tolerance in [1e-2, 1e-3].iter() {
+            let step = Step::Fixed(0.05).adaptive(*tolerance);
+            let splines = data.interpolate_step(step, Scheme::Linear).unwrap();
+            let steps = splines.steps();
+            assert!((steps.iter().sum::<f64>() - 22.).abs() < 1e-9);
+            assert!(steps
+                .iter()
+                .all(|ds| *ds >= 0.05 - 1e-12 && *ds <= 1. + 1e-12));
+            // coarse on the straight run, fine on bends.
+            let straight = steps.iter().cloned().fold(0., f64::max);
+            assert_eq!(1., straight);
+            assert!(steps[0] < straight && steps[steps.len() - 1] < straight);
+
+            let metrics = metrics::evaluate(&positions(&splines), &reference).unwrap();
+            assert!(steps.len() < fixed.splines.len() / 2, "{}", steps.len());
+            assert!(
+                metrics.tip_error < fixed_error + tolerance,
+                "{}: {:?}, fixed {}",
+                tolerance,
+                metrics,
+                fixed_error
+            );
+        }
+
+        // perturbed data share the steps.
+        let splines = data
+            .interpolate_steps(fixed.steps(), Scheme::Linear)
+            .unwrap();
+        assert_eq!(fixed.splines, splines.splines);
+        assert_eq!(
+            Some(ReconstructError::InvalidStep(0.)),
+            data.interpolate_step(
+                Step::Adaptive {
+                    tolerance: 0.,
+                    min: 0.01,
+                    max: 0.1
+                },
+                Scheme::Linear
+            )
+            .err()
+        );
+    }
+
+    #[test]
+    fn adaptive_step_to_last_station() {
+        // start + (end - start) rounds past end for these stations.
+        for (start, end) in [(0.3, 0.9), (0.7, 2.9)].iter() {
+            assert!(start + (end - start) > *end);
+            let data = [(*start, 0.1, 0.), (*end, 0.1, 0.)];
+            // a single step from start to end.
+            let step = Step::Adaptive {
+                tolerance: 1.,
+                min: 0.01,
+                max: 10.,
+            };
+            let splines = data.interpolate_step(step, Scheme::Linear).unwrap();
+            assert_eq!(1, splines.steps().len());
+            assert!((splines.length() - (end - start)).abs() < 1e-12);
+        }
+    }
+
     #[test]
     fn single_cos_error() {
         let mut rng = SmallRng::from_entropy();
@@ -773,6 +1586,7 @@ mod tests {
             let data = raw_data
                 .set_error(1, (1. + 0.1 * index as f64, 1.))
                 .interpolate(0.01) // linear interpolate; ds = 0.01.
+                .unwrap()
                 .frenet_reconstruct(
                     Vector3::new(0., 0., 1.),                          // initialized coordinate
                     Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -793,9 +1607,9 @@ mod tests {
         }
 
         let error_v = error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(-0., 2.)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         // The 'view' describes what set of data is drawn
@@ -842,6 +1656,7 @@ mod tests {
             let data = raw_data
                 .set_error(index, (1.4, 1.))
                 .interpolate(0.01) // linear interpolate; ds = 0.01.
+                .unwrap()
                 .frenet_reconstruct(
                     Vector3::new(0., 0., 1.),                          // initialized coordinate
                     Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -864,9 +1679,9 @@ mod tests {
         }
 
         let error_v = error_view
-            .x_range(0., 7.)
+            .x_range(0., 8.)
             .y_range(-0., 2.)
-            .x_label("x")
+            .x_label("s")
             .y_label("error");
 
         // The 'view' describes what set of data is drawn
@@ -886,10 +1701,6 @@ mod tests {
             .unwrap();
     }
 
-    fn sum_error(theta: f64, (x, y): (&f64, &f64)) -> f64 {
-        ((*x - theta.sin()).powi(2) + (*y - theta.cos()).powi(2)).sqrt()
-    }
-
     #[test]
     fn single_circle_error() {
         let mut rng = SmallRng::from_entropy();
@@ -919,6 +1730,7 @@ mod tests {
             let data: Vec<_> = raw_data
                 .set_error(1, (1. + 0.1 * index as f64, 1.))
                 .interpolate(STEP) // linear interpolate; ds = 0.01.
+                .unwrap()
                 .frenet_reconstruct(
                     Vector3::new(0., 0., 1.),                          // initialized coordinate
                     Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -927,14 +1739,7 @@ mod tests {
                 .into_iter()
                 .map(|point| (-point.x as f64, point.z as f64))
                 .collect();
-            let errors = data
-                .iter()
-                .enumerate()
-                .map(|(index, (x, y))| {
-                    let theta = index as f64 * STEP;
-                    (theta, sum_error(theta, (x as &f64, y as &f64)))
-                })
-                .collect();
+            let errors = circle_errors(&data);
 
             let legend = format!("curvature error {:.1}", 1. + 0.1 * index as f64);
             view = view.add(
@@ -999,6 +1804,7 @@ mod tests {
             let data: Vec<_> = raw_data
                 .set_error(index, (1.2, 1.))
                 .interpolate(0.01) // linear interpolate; ds = 0.01.
+                .unwrap()
                 .frenet_reconstruct(
                     Vector3::new(0., 0., 1.),                          // initialized coordinate
                     Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
@@ -1008,14 +1814,7 @@ mod tests {
                 .map(|point| (-point.x as f64, point.z as f64))
                 .collect();
 
-            let errors = data
-                .iter()
-                .enumerate()
-                .map(|(index, (x, y))| {
-                    let theta = index as f64 * STEP;
-                    (theta, sum_error(theta, (x as &f64, y as &f64)))
-                })
-                .collect();
+            let errors = circle_errors(&data);
 
             let legend = format!("curvature error s={:.4}", raw_data[index].0);
 
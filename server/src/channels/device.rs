@@ -0,0 +1,302 @@
+use super::curvature_splines::{validate, CurvatureSplines, PointSlice, Step};
+use super::error::ReconstructError;
+use super::interpolation::Scheme;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Curvature of uninstrumented ends, beyond the first and the last sensor.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extrapolation {
+    /// Curvature of the nearest sensor.
+    #[default]
+    Constant,
+
+    /// Linear extrapolation of the two nearest sensors.
+    Linear,
+
+    /// Zero curvature right beyond the nearest sensor, like a straight end.
+    Zero,
+}
+
+/// Physical geometry of a device, in unit of s.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Device {
+    /// distance from the base to the tip; the tip is the last sensor if None.
+    pub length: Option<f64>,
+
+    /// distance from the base to the first sensor.
+    pub offset: f64,
+
+    pub extrapolation: Extrapolation,
+}
+
+impl Device {
+    /// Move stations to distance from the base, then extrapolate stations at the base and the tip.
+    ///
+    /// With zero extrapolation, only stations of sensors are kept;
+    /// `interpolate` adds straight ends.
+    pub fn extend(
+        &self,
+        data: &[(f64, f64, f64)],
+    ) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+        validate(data)?;
+        let start = data[0].0;
+        let mut stations = Vec::with_capacity(data.len() + 2);
+        stations.extend(
+            data.iter()
+                .map(|(s, ka, kb)| (s - start + self.offset, *ka, *kb)),
+        );
+        if self.offset > 0. {
+            let base = self.extrapolate(stations[0], stations[1], 0.);
+            stations.splice(0..0, base);
+        }
+        let n = stations.len();
+        let last = stations[n - 1].0;
+        match self.length {
+            Some(length) if length < last => {
+                return Err(ReconstructError::BeyondDevice { s: last, length })
+            }
+            Some(length) if length > last => {
+                let tip = self.extrapolate(stations[n - 1], stations[n - 2], length);
+                stations.extend(tip);
+            }
+            _ => (),
+        }
+        Ok(stations)
+    }
+
+    /// Extend raw data over the device, then interpolate with fixed or adaptive steps.
+    ///
+    /// With zero extrapolation, ends beyond sensors are straight steps around the interpolated
+    /// sensors, so no scheme overshoots at the drop of curvature.
+    pub fn interpolate(
+        &self,
+        data: &[(f64, f64, f64)],
+        step: Step,
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError> {
+        let stations = self.extend(data)?;
+        let splines = stations.interpolate_step(step, scheme)?;
+        if self.extrapolation != Extrapolation::Zero {
+            return Ok(splines);
+        }
+        let (first, last) = (stations[0].0, stations[stations.len() - 1].0);
+        let tip = self.length.unwrap_or(last) - last;
+        Ok(
+            CurvatureSplines::straight(straight_steps(first, step.longest()))
+                .chain(splines)
+                .chain(CurvatureSplines::straight(straight_steps(
+                    tip,
+                    step.longest(),
+                ))),
+        )
+    }
+
+    /// Extend raw data over the device, then interpolate at the start of each step from the base,
+    /// e.g. steps of the curve of another frame.
+    pub fn interpolate_steps(
+        &self,
+        data: &[(f64, f64, f64)],
+        steps: &[f64],
+        scheme: Scheme,
+    ) -> Result<CurvatureSplines, ReconstructError> {
+        let stations = self.extend(data)?;
+        if self.extrapolation != Extrapolation::Zero {
+            return stations.interpolate_steps(steps, scheme);
+        }
+        // steps starting before the first sensor or at the last one are straight ends.
+        let (first, last) = (stations[0].0, stations[stations.len() - 1].0);
+        let tolerance = last * 1e-9;
+        let (mut base, mut span, mut s) = (0, 0, 0.);
+        for ds in steps {
+            if s < first - tolerance {
+                base += 1;
+            } else if s < last - tolerance {
+                span += 1;
+            }
+            s += ds;
+        }
+        Ok(CurvatureSplines::straight(steps[..base].to_vec())
+            .chain(stations.interpolate_steps(&steps[base..base + span], scheme)?)
+            .chain(CurvatureSplines::straight(steps[base + span..].to_vec())))
+    }
+
+    /// Station at s from the nearer station `a` and the farther station `b`,
+    /// or none with zero extrapolation.
+    fn extrapolate(
+        &self,
+        a: (f64, f64, f64),
+        b: (f64, f64, f64),
+        s: f64,
+    ) -> Option<(f64, f64, f64)> {
+        match self.extrapolation {
+            Extrapolation::Constant => Some((s, a.1, a.2)),
+            Extrapolation::Linear => {
+                let t = (s - a.0) / (a.0 - b.0);
+                Some((s, a.1 + (a.1 - b.1) * t, a.2 + (a.2 - b.2) * t))
+            }
+            Extrapolation::Zero => None,
+        }
+    }
+}
+
+/// Steps of at most ds over length, the last one shortened.
+fn straight_steps(length: f64, ds: f64) -> Vec<f64> {
+    if length <= 0. {
+        return Vec::new();
+    }
+    let count = ((length / ds) * (1. - 1e-9)).ceil().max(1.) as usize;
+    let mut steps = vec![ds; count];
+    steps[count - 1] = length - (count - 1) as f64 * ds;
+    steps
+}
+
+impl FromStr for Extrapolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(Extrapolation::Constant),
+            "linear" => Ok(Extrapolation::Linear),
+            "zero" => Ok(Extrapolation::Zero),
+            _ => Err(format!("unknown extrapolation: {}", s)),
+        }
+    }
+}
+
+impl Display for Extrapolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Extrapolation::Constant => "constant",
+            Extrapolation::Linear => "linear",
+            Extrapolation::Zero => "zero",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, Extrapolation};
+    use crate::channels::curvature_splines::{PointSlice, Step};
+    use crate::channels::error::ReconstructError;
+    use crate::channels::interpolation::Scheme;
+    use num::{One, Zero};
+
+    const DATA: [(f64, f64, f64); 3] = [(10., 0.1, 0.), (12., 0.2, -0.1), (14., 0.4, 0.)];
+
+    #[test]
+    fn extend() {
+        let device = |extrapolation| Device {
+            length: Some(10.),
+            offset: 3.,
+            extrapolation,
+        };
+        assert_eq!(
+            vec![
+                (0., 0.1, 0.),
+                (3., 0.1, 0.),
+                (5., 0.2, -0.1),
+                (7., 0.4, 0.),
+                (10., 0.4, 0.)
+            ],
+            device(Extrapolation::Constant).extend(&DATA).unwrap()
+        );
+        let linear = device(Extrapolation::Linear).extend(&DATA).unwrap();
+        let close = |p: (f64, f64, f64), q: (f64, f64, f64)| {
+            (p.0 - q.0).abs() < 1e-12 && (p.1 - q.1).abs() < 1e-12 && (p.2 - q.2).abs() < 1e-12
+        };
+        assert!(close((0., -0.05, 0.15), linear[0]), "{:?}", linear[0]);
+        assert!(close((10., 0.7, 0.15), linear[4]), "{:?}", linear[4]);
+        assert_eq!(
+            vec![(3., 0.1, 0.), (5., 0.2, -0.1), (7., 0.4, 0.)],
+            device(Extrapolation::Zero).extend(&DATA).unwrap()
+        );
+
+        // sensors cover the device.
+        assert_eq!(
+            vec![(0., 0.1, 0.), (2., 0.2, -0.1), (4., 0.4, 0.)],
+            Device::default().extend(&DATA).unwrap()
+        );
+        assert_eq!(
+            Some(ReconstructError::BeyondDevice { s: 7., length: 6. }),
+            Device {
+                length: Some(6.),
+                ..device(Extrapolation::Constant)
+            }
+            .extend(&DATA)
+            .err()
+        );
+    }
+
+    #[test]
+    fn cover_device() {
+        // a straight device reaches its tip.
+        let device = Device {
+            length: Some(10.),
+            offset: 3.,
+            extrapolation: Extrapolation::Linear,
+        };
+        let data = [(0., 0., 0.), (4., 0., 0.)];
+        let points = device
+            .extend(&data)
+            .unwrap()
+            .interpolate(0.05)
+            .unwrap()
+            .frenet_reconstruct(Zero::zero(), One::one())
+            .unwrap();
+        let tip = points.last().unwrap();
+        assert!((tip.z - 10.).abs() < 1e-5, "{:?}", tip);
+
+        assert_eq!(Ok(Extrapolation::Zero), "zero".parse());
+        assert!("quadratic".parse::<Extrapolation>().is_err());
+        assert_eq!("linear", Extrapolation::Linear.to_string());
+    }
+
+    #[test]
+    fn zero_ends() {
+        let device = Device {
+            length: Some(10.),
+            offset: 3.,
+            extrapolation: Extrapolation::Zero,
+        };
+        let schemes = [
+            Scheme::Linear,
+            Scheme::CatmullRom,
+            Scheme::CubicHermite,
+            Scheme::NaturalCubic,
+            Scheme::Akima,
+            Scheme::Monotone,
+        ];
+        for scheme in schemes.iter() {
+            for step in [Step::Fixed(0.05), Step::Fixed(0.05).adaptive(1e-3)].iter() {
+                let splines = device.interpolate(&DATA, *step, *scheme).unwrap();
+                assert!((splines.length() - 10.).abs() < 1e-9, "{}", scheme);
+                let mut s = 0.;
+                for ((ka, kb), ds) in splines.splines.iter().zip(splines.steps()) {
+                    // straight beyond sensors, bounded by readings between them.
+                    if !(3. - 1e-9..7. - 1e-9).contains(&s) {
+                        assert_eq!((0., 0.), (*ka, *kb), "{} at {}", scheme, s);
+                    }
+                    assert!(ka.abs() <= 0.5 && kb.abs() <= 0.2, "{} at {}", scheme, s);
+                    s += ds;
+                }
+
+                // perturbed data share the steps.
+                let shared = device
+                    .interpolate_steps(&DATA, splines.steps(), *scheme)
+                    .unwrap();
+                assert_eq!(splines.splines.len(), shared.splines.len(), "{}", scheme);
+                for (p, q) in splines.splines.iter().zip(shared.splines.iter()) {
+                    assert!(
+                        (p.0 - q.0).abs() < 1e-9 && (p.1 - q.1).abs() < 1e-9,
+                        "{}",
+                        scheme
+                    );
+                }
+            }
+        }
+    }
+}
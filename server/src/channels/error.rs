@@ -0,0 +1,126 @@
+use std::fmt::{self, Display};
+
+/// Error in interpolation or reconstruction of a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconstructError {
+    /// data set is empty.
+    Empty,
+
+    /// data set has too few stations to reconstruct.
+    TooFewSamples { required: usize, found: usize },
+
+    /// arc length of the station at `index` is not greater than the previous one.
+    NonMonotonic { index: usize, s: f64 },
+
+    /// arc length or curvature of the station at `index` is NaN or infinite.
+    NonFinite { index: usize },
+
+    /// delta s is not positive and finite.
+    InvalidStep(f64),
+
+    /// sample point is out of range of stations.
+    OutOfRange(f64),
+
+    /// rotation matrix cannot be inverted.
+    SingularRotation,
+
+    /// number of calibrated sensors is not equal to number of stations.
+    CalibrationMismatch { sensors: usize, found: usize },
+
+    /// number of sensors in noise model is neither one nor number of stations.
+    NoiseMismatch { sensors: usize, found: usize },
+
+    /// polyline folds back at vertex `index`.
+    Cusp { index: usize },
+
+    /// number of core readings of the station at `index` is not equal to number of cores.
+    CoreMismatch {
+        index: usize,
+        cores: usize,
+        found: usize,
+    },
+
+    /// number of reconstructed points is not equal to number of steps.
+    PointMismatch { steps: usize, found: usize },
+
+    /// sensor at distance `s` from the base is beyond the tip of device.
+    BeyondDevice { s: f64, length: f64 },
+
+    /// number of segments in frame is not equal to number of segments in structure.
+    SegmentMismatch { segments: usize, found: usize },
+
+    /// sequence number of frame is not greater than that of the last frame.
+    Stale { seq: u64, last: u64 },
+
+    /// frame of segments is sent to a channel without structure.
+    NoStructure,
+
+    /// position or orientation of pose is NaN or infinite.
+    NonFinitePose,
+}
+
+impl Display for ReconstructError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconstructError::Empty => f.write_str("data set cannot be empty"),
+            ReconstructError::TooFewSamples { required, found } => write!(
+                f,
+                "too few samples: required at least {}, found {}",
+                required, found
+            ),
+            ReconstructError::NonMonotonic { index, s } => write!(
+                f,
+                "arc length is not increasing at station {} (s = {})",
+                index, s
+            ),
+            ReconstructError::NonFinite { index } => {
+                write!(f, "NaN or infinite value at station {}", index)
+            }
+            ReconstructError::InvalidStep(ds) => write!(f, "invalid step: {}", ds),
+            ReconstructError::OutOfRange(s) => write!(f, "sample out of range: {}", s),
+            ReconstructError::SingularRotation => f.write_str("rotation matrix is singular"),
+            ReconstructError::CalibrationMismatch { sensors, found } => write!(
+                f,
+                "calibration has {} sensors, but frame has {} stations",
+                sensors, found
+            ),
+            ReconstructError::NoiseMismatch { sensors, found } => write!(
+                f,
+                "noise model has {} sensors, but frame has {} stations",
+                sensors, found
+            ),
+            ReconstructError::Cusp { index } => {
+                write!(f, "polyline folds back at vertex {}", index)
+            }
+            ReconstructError::CoreMismatch {
+                index,
+                cores,
+                found,
+            } => write!(
+                f,
+                "expect {} core readings at station {}, found {}",
+                cores, index, found
+            ),
+            ReconstructError::PointMismatch { steps, found } => {
+                write!(f, "expect {} reconstructed points, found {}", steps, found)
+            }
+            ReconstructError::BeyondDevice { s, length } => write!(
+                f,
+                "sensor at {} is beyond the tip of device of length {}",
+                s, length
+            ),
+            ReconstructError::SegmentMismatch { segments, found } => write!(
+                f,
+                "structure has {} segments, but frame has {}",
+                segments, found
+            ),
+            ReconstructError::Stale { seq, last } => {
+                write!(f, "frame {} is not after the last frame {}", seq, last)
+            }
+            ReconstructError::NoStructure => f.write_str("channel has no structure of segments"),
+            ReconstructError::NonFinitePose => f.write_str("pose is NaN or infinite"),
+        }
+    }
+}
+
+impl std::error::Error for ReconstructError {}
@@ -0,0 +1,226 @@
+use super::error::ReconstructError;
+use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Position of a fibre core in the cross section.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Core {
+    /// radial distance from the neutral axis, in unit of s.
+    pub radius: f64,
+
+    /// angular position in radians, counterclockwise from the a axis.
+    pub angle: f64,
+}
+
+/// Physical quantity read from each core.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Reading {
+    /// Strain.
+    #[default]
+    Strain,
+
+    /// Bragg wavelength shift, strain is `shift / (wavelength * (1 - photo_elastic))`.
+    WavelengthShift {
+        wavelength: f64,
+        #[serde(default = "default_photo_elastic")]
+        photo_elastic: f64,
+    },
+}
+
+/// Geometry of a multi-core fibre, e.g.
+/// `{"cores": [{"radius": 0.035, "angle": 0}, ...], "reading": {"kind": "strain"}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreGeometry {
+    pub cores: Vec<Core>,
+    #[serde(default)]
+    pub reading: Reading,
+}
+
+/// Least square solver of curvature from strain of cores.
+///
+/// Strain of a core is `e + radius * (ka * cos(angle) + kb * sin(angle))`,
+/// where the common mode `e` of temperature and axial strain is dropped.
+#[derive(Debug, Clone)]
+pub struct StrainModel {
+    geometry: CoreGeometry,
+
+    // pseudo inverse mapping strains of cores to (e, ka, kb).
+    solver: DMatrix<f64>,
+}
+
+/// Effective photo-elastic coefficient of silica fibre.
+fn default_photo_elastic() -> f64 {
+    0.22
+}
+
+impl StrainModel {
+    /// Return None if cores cannot tell bending from common mode,
+    /// e.g. less than three cores, or all cores on a line through the center.
+    pub fn new(geometry: CoreGeometry) -> Option<Self> {
+        let cores = &geometry.cores;
+        let scale = cores
+            .iter()
+            .map(|core| core.radius.abs())
+            .fold(0., f64::max);
+        let finite = cores
+            .iter()
+            .all(|core| core.radius.is_finite() && core.angle.is_finite());
+        if cores.len() < 3 || !finite || scale == 0. {
+            return None;
+        }
+        // columns are normalized by the largest radius, to compare their singular values.
+        let design = DMatrix::from_fn(cores.len(), 3, |i, j| match j {
+            0 => 1.,
+            1 => cores[i].radius * cores[i].angle.cos() / scale,
+            _ => cores[i].radius * cores[i].angle.sin() / scale,
+        });
+        let svd = design.svd(true, true);
+        if svd.singular_values.min() <= 1e-6 * svd.singular_values.max() {
+            return None;
+        }
+        let mut solver = svd.pseudo_inverse(0.).ok()?;
+        for j in 0..cores.len() {
+            solver[(1, j)] /= scale;
+            solver[(2, j)] /= scale;
+        }
+        Some(Self { geometry, solver })
+    }
+
+    /// Load geometry from a json file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let geometry = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Self::new(geometry).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cores cannot resolve bending from common mode",
+            )
+        })
+    }
+
+    /// Convert stations of `[s, reading of each core...]` to (distance, ka, kb).
+    pub fn curvature(&self, data: &[Vec<f64>]) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+        let cores = self.geometry.cores.len();
+        let factor = match self.geometry.reading {
+            Reading::Strain => 1.,
+            Reading::WavelengthShift {
+                wavelength,
+                photo_elastic,
+            } => 1. / (wavelength * (1. - photo_elastic)),
+        };
+        data.iter()
+            .enumerate()
+            .map(|(index, station)| {
+                if station.len() != cores + 1 {
+                    return Err(ReconstructError::CoreMismatch {
+                        index,
+                        cores,
+                        found: station.len().saturating_sub(1),
+                    });
+                }
+                let strain = DVector::from_iterator(cores, station[1..].iter().map(|v| v * factor));
+                let solution = &self.solver * strain;
+                Ok((station[0], solution[1], solution[2]))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Core, CoreGeometry, Reading, StrainModel};
+    use crate::channels::error::ReconstructError;
+    use std::f64::consts::PI;
+
+    fn geometry(angles: &[f64], reading: Reading) -> CoreGeometry {
+        CoreGeometry {
+            cores: angles
+                .iter()
+                .map(|angle| Core {
+                    radius: 0.035,
+                    angle: *angle,
+                })
+                .collect(),
+            reading,
+        }
+    }
+
+    /// Strain of cores with common mode e.
+    fn strain(geometry: &CoreGeometry, (s, ka, kb): (f64, f64, f64), e: f64) -> Vec<f64> {
+        let mut station = vec![s];
+        station.extend(
+            geometry
+                .cores
+                .iter()
+                .map(|core| e + core.radius * (ka * core.angle.cos() + kb * core.angle.sin())),
+        );
+        station
+    }
+
+    #[test]
+    fn solve() {
+        let expected = [(0., 0.1, -0.2), (5., 0.02, 0.3), (10., -0.4, 0.)];
+        for angles in [
+            vec![0., 2. * PI / 3., 4. * PI / 3.],
+            vec![0.1, PI / 2. + 0.1, PI + 0.1, 3. * PI / 2. + 0.1],
+            vec![0., 0.5, 2., 4.],
+        ]
+        .iter()
+        {
+            let geometry = geometry(angles, Reading::Strain);
+            let data = expected
+                .iter()
+                .enumerate()
+                .map(|(i, station)| strain(&geometry, *station, 1e-3 * i as f64))
+                .collect::<Vec<_>>();
+            let model = StrainModel::new(geometry).unwrap();
+            for (p, q) in model.curvature(&data).unwrap().iter().zip(expected.iter()) {
+                assert!((p.0 - q.0).abs() < 1e-12, "{:?} {:?}", p, q);
+                assert!((p.1 - q.1).abs() < 1e-9, "{:?} {:?}", p, q);
+                assert!((p.2 - q.2).abs() < 1e-9, "{:?} {:?}", p, q);
+            }
+        }
+    }
+
+    #[test]
+    fn wavelength_shift() {
+        let reading = Reading::WavelengthShift {
+            wavelength: 1550.,
+            photo_elastic: 0.22,
+        };
+        let geometry = geometry(&[0., 2. * PI / 3., 4. * PI / 3.], reading);
+        let data = vec![strain(&geometry, (0., 0.1, 0.2), 2e-4)
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i == 0 { *v } else { v * 1550. * 0.78 })
+            .collect::<Vec<_>>()];
+        let (_, ka, kb) = StrainModel::new(geometry)
+            .unwrap()
+            .curvature(&data)
+            .unwrap()[0];
+        assert!(
+            (ka - 0.1).abs() < 1e-9 && (kb - 0.2).abs() < 1e-9,
+            "{} {}",
+            ka,
+            kb
+        );
+    }
+
+    #[test]
+    fn reject() {
+        assert!(StrainModel::new(geometry(&[0., PI], Reading::Strain)).is_none());
+        assert!(StrainModel::new(geometry(&[0., PI, 0.], Reading::Strain)).is_none());
+        let model = StrainModel::new(geometry(&[0., 1., 2.], Reading::Strain)).unwrap();
+        assert_eq!(
+            Err(ReconstructError::CoreMismatch {
+                index: 1,
+                cores: 3,
+                found: 2
+            }),
+            model.curvature(&[vec![0., 0., 0., 0.], vec![1., 0., 0.]])
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::f64::consts::PI;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Temporal filter of curvature, applied to ka and kb of each station.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Filter {
+    /// No filtering.
+    #[default]
+    None,
+
+    /// Exponential moving average, larger alpha follows faster.
+    Ema { alpha: f64 },
+
+    /// One-euro filter, cutoff frequency in Hz rises with speed of curvature by beta.
+    OneEuro {
+        min_cutoff: f64,
+        beta: f64,
+        d_cutoff: f64,
+    },
+
+    /// Kalman filter of random walk, with process noise per second and measurement noise.
+    Kalman {
+        process_noise: f64,
+        measurement_noise: f64,
+    },
+}
+
+/// State of a scalar filter.
+#[derive(Debug, Copy, Clone)]
+struct Scalar {
+    value: f64,
+    // filtered derivative, of one-euro filter
+    derivative: f64,
+    // estimate variance, of Kalman filter
+    variance: f64,
+}
+
+/// Filter state of a channel, kept across frames.
+#[derive(Debug, Clone, Default)]
+pub struct FilterState {
+    // (ka, kb) of each station
+    stations: Vec<[Scalar; 2]>,
+}
+
+impl Scalar {
+    fn new(value: f64, filter: &Filter) -> Self {
+        let variance = match *filter {
+            Filter::Kalman {
+                measurement_noise, ..
+            } => measurement_noise,
+            _ => 0.,
+        };
+        Self {
+            value,
+            derivative: 0.,
+            variance,
+        }
+    }
+
+    /// Filter a new measurement after dt seconds.
+    fn update(&mut self, filter: &Filter, measurement: f64, dt: f64) -> f64 {
+        // smoothing factor of a low pass filter.
+        let smoothing = |cutoff: f64| 1. / (1. + 1. / (2. * PI * cutoff * dt));
+        match *filter {
+            Filter::None => self.value = measurement,
+            Filter::Ema { alpha } => self.value += alpha * (measurement - self.value),
+            Filter::OneEuro {
+                min_cutoff,
+                beta,
+                d_cutoff,
+            } => {
+                let derivative = (measurement - self.value) / dt;
+                self.derivative += smoothing(d_cutoff) * (derivative - self.derivative);
+                let cutoff = min_cutoff + beta * self.derivative.abs();
+                self.value += smoothing(cutoff) * (measurement - self.value);
+            }
+            Filter::Kalman {
+                process_noise,
+                measurement_noise,
+            } => {
+                let prior = self.variance + process_noise * dt;
+                let gain = prior / (prior + measurement_noise);
+                self.value += gain * (measurement - self.value);
+                self.variance = (1. - gain) * prior;
+            }
+        }
+        self.value
+    }
+}
+
+/// Shortest interval between frames, in seconds.
+const MIN_INTERVAL: f64 = 1e-3;
+
+impl FilterState {
+    /// Filter a frame received dt seconds after the last one.
+    ///
+    /// State is reset when number of stations changes, and the frame passes through.
+    pub fn apply(
+        &mut self,
+        filter: &Filter,
+        data: &[(f64, f64, f64)],
+        dt: f64,
+    ) -> Vec<(f64, f64, f64)> {
+        if self.stations.len() != data.len() {
+            self.stations = data
+                .iter()
+                .map(|(_, ka, kb)| [Scalar::new(*ka, filter), Scalar::new(*kb, filter)])
+                .collect();
+            return data.to_vec();
+        }
+        let dt = dt.max(MIN_INTERVAL);
+        self.stations
+            .iter_mut()
+            .zip(data.iter())
+            .map(|([a, b], (s, ka, kb))| (*s, a.update(filter, *ka, dt), b.update(filter, *kb, dt)))
+            .collect()
+    }
+
+    pub fn reset(&mut self) {
+        self.stations.clear()
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Parse filter like `ema:0.5`, `one-euro:1,0.5,1` or `kalman:0.001,0.0001`,
+    /// parameters are optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, params) = match s.find(':') {
+            Some(index) => (&s[..index], &s[index + 1..]),
+            None => (s, ""),
+        };
+        let params = params
+            .split(',')
+            .filter(|param| !param.trim().is_empty())
+            .map(|param| param.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("invalid filter parameter: {}", err))?;
+        if params
+            .iter()
+            .any(|param| !(*param >= 0. && param.is_finite()))
+        {
+            return Err(format!("filter parameters should be non-negative: {}", s));
+        }
+        let param = |index: usize, default: f64| params.get(index).cloned().unwrap_or(default);
+        let (filter, max_params) = match name {
+            "none" => (Filter::None, 0),
+            "ema" => (
+                Filter::Ema {
+                    alpha: param(0, 0.5),
+                },
+                1,
+            ),
+            "one-euro" => (
+                Filter::OneEuro {
+                    min_cutoff: param(0, 1.),
+                    beta: param(1, 0.5),
+                    d_cutoff: param(2, 1.),
+                },
+                3,
+            ),
+            "kalman" => (
+                Filter::Kalman {
+                    process_noise: param(0, 1e-3),
+                    measurement_noise: param(1, 1e-4),
+                },
+                2,
+            ),
+            _ => return Err(format!("unknown filter: {}", name)),
+        };
+        if params.len() > max_params {
+            return Err(format!("too many filter parameters: {}", s));
+        }
+        let valid = match filter {
+            Filter::None => true,
+            Filter::Ema { alpha } => alpha > 0. && alpha <= 1.,
+            Filter::OneEuro {
+                min_cutoff,
+                d_cutoff,
+                ..
+            } => min_cutoff > 0. && d_cutoff > 0.,
+            Filter::Kalman {
+                measurement_noise, ..
+            } => measurement_noise > 0.,
+        };
+        if valid {
+            Ok(filter)
+        } else {
+            Err(format!("invalid filter parameters: {}", s))
+        }
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::None => f.write_str("none"),
+            Filter::Ema { alpha } => write!(f, "ema:{}", alpha),
+            Filter::OneEuro {
+                min_cutoff,
+                beta,
+                d_cutoff,
+            } => write!(f, "one-euro:{},{},{}", min_cutoff, beta, d_cutoff),
+            Filter::Kalman {
+                process_noise,
+                measurement_noise,
+            } => write!(f, "kalman:{},{}", process_noise, measurement_noise),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FilterState};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    const DT: f64 = 1. / 60.;
+
+    /// Standard deviation of ka after a noisy constant signal, and ka after a step.
+    fn response(filter: &Filter) -> (f64, f64) {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut state = FilterState::default();
+        let mut outputs = Vec::new();
+        for _ in 0..600 {
+            let ka = 0.1 + rng.gen_range(-0.01, 0.01);
+            outputs.push(state.apply(filter, &[(0., ka, 0.), (5., 0., ka)], DT)[0].1);
+        }
+        let mean = outputs[300..].iter().sum::<f64>() / 300.;
+        let deviation = (outputs[300..]
+            .iter()
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>()
+            / 300.)
+            .sqrt();
+        let mut step = 0.;
+        for _ in 0..30 {
+            step = state.apply(filter, &[(0., 0.2, 0.), (5., 0., 0.2)], DT)[0].1;
+        }
+        (deviation, step)
+    }
+
+    #[test]
+    fn smooth() {
+        let (raw, step) = response(&Filter::None);
+        assert_eq!(0.2, step);
+        for filter in [
+            Filter::Ema { alpha: 0.2 },
+            "one-euro".parse().unwrap(),
+            "kalman:0.001,0.0003".parse().unwrap(),
+        ]
+        .iter()
+        {
+            let (deviation, step) = response(filter);
+            assert!(deviation < raw / 2., "{}: {} / {}", filter, deviation, raw);
+            // half a second after the step.
+            assert!((step - 0.2).abs() < 0.01, "{}: {}", filter, step);
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let filter = Filter::Ema { alpha: 0.5 };
+        let mut state = FilterState::default();
+        assert_eq!(
+            vec![(0., 1., 0.)],
+            state.apply(&filter, &[(0., 1., 0.)], DT)
+        );
+        assert_eq!(
+            vec![(0., 0.5, 0.)],
+            state.apply(&filter, &[(0., 0., 0.)], DT)
+        );
+        // stations changed.
+        assert_eq!(
+            vec![(0., 0., 0.), (1., 1., 1.)],
+            state.apply(&filter, &[(0., 0., 0.), (1., 1., 1.)], DT)
+        );
+        state.reset();
+        assert_eq!(
+            vec![(0., 1., 1.), (1., 1., 1.)],
+            state.apply(&filter, &[(0., 1., 1.), (1., 1., 1.)], DT)
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Ok(Filter::None), "none".parse());
+        assert_eq!(Ok(Filter::Ema { alpha: 0.3 }), "ema:0.3".parse());
+        assert_eq!(
+            Ok(Filter::OneEuro {
+                min_cutoff: 2.,
+                beta: 0.5,
+                d_cutoff: 1.
+            }),
+            "one-euro:2".parse()
+        );
+        assert_eq!(
+            Ok(Filter::Kalman {
+                process_noise: 0.1,
+                measurement_noise: 0.01
+            }),
+            "kalman:0.1, 0.01".parse()
+        );
+        for filter in [
+            "ema:0",
+            "ema:2",
+            "ema:0.1,0.2",
+            "kalman:1,-1",
+            "median",
+            "ema:x",
+        ]
+        .iter()
+        {
+            assert!(filter.parse::<Filter>().is_err(), "{}", filter);
+        }
+        let filter = "one-euro:1,0.1,2".parse::<Filter>().unwrap();
+        assert_eq!(Ok(filter), filter.to_string().parse());
+    }
+}
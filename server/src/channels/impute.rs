@@ -0,0 +1,211 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Policy of filling missing (null or NaN) curvature of sensors.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Imputation {
+    /// Reject frames with missing values.
+    #[default]
+    Reject,
+
+    /// Linear interpolate by arc length between the nearest good stations,
+    /// or copy the nearest good station at ends.
+    Neighbours,
+
+    /// Take the value of the last frame of the same stations,
+    /// or reject the frame if there is none, e.g. after the sensor layout changed.
+    LastFrame,
+}
+
+/// Relative tolerance of arc length of the same station in two frames.
+const STATION_TOLERANCE: f64 = 1e-9;
+
+/// Whether two frames have the same stations.
+fn same_stations(data: &[(f64, f64, f64)], last: &[(f64, f64, f64)]) -> bool {
+    data.len() == last.len()
+        && data
+            .iter()
+            .zip(last.iter())
+            .all(|((s, _, _), (last, _, _))| {
+                (s - last).abs() <= STATION_TOLERANCE * s.abs().max(last.abs()).max(1.)
+            })
+}
+
+impl Imputation {
+    /// Fill missing ka and kb of stations in place, return indices of imputed stations.
+    ///
+    /// Values that cannot be filled are left as NaN, and the frame is rejected later.
+    pub fn apply(
+        self,
+        data: &mut [(f64, f64, f64)],
+        last: Option<&[(f64, f64, f64)]>,
+    ) -> Vec<usize> {
+        let missing = data
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ka, kb))| !(ka.is_finite() && kb.is_finite()))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Vec::new();
+        }
+        match self {
+            Imputation::Reject => return Vec::new(),
+            Imputation::LastFrame => {
+                let last = match last {
+                    Some(last) if same_stations(data, last) => last,
+                    _ => return Vec::new(),
+                };
+                for index in missing.iter().cloned() {
+                    let (_, ka, kb) = &mut data[index];
+                    if !ka.is_finite() {
+                        *ka = last[index].1;
+                    }
+                    if !kb.is_finite() {
+                        *kb = last[index].2;
+                    }
+                }
+            }
+            Imputation::Neighbours => {
+                let s = data.iter().map(|station| station.0).collect::<Vec<_>>();
+                let mut ka = data.iter().map(|station| station.1).collect::<Vec<_>>();
+                let mut kb = data.iter().map(|station| station.2).collect::<Vec<_>>();
+                fill(&s, &mut ka);
+                fill(&s, &mut kb);
+                for (station, (ka, kb)) in data.iter_mut().zip(ka.into_iter().zip(kb)) {
+                    station.1 = ka;
+                    station.2 = kb;
+                }
+            }
+        }
+        missing
+            .into_iter()
+            .filter(|index| data[*index].1.is_finite() && data[*index].2.is_finite())
+            .collect()
+    }
+}
+
+/// Fill non-finite values at arc length `s` from good neighbours.
+fn fill(s: &[f64], values: &mut [f64]) {
+    let good = (0..s.len())
+        .filter(|i| s[*i].is_finite() && values[*i].is_finite())
+        .collect::<Vec<_>>();
+    if good.is_empty() {
+        return;
+    }
+    // index of the next good station in `good`.
+    let mut next = 0;
+    for i in 0..s.len() {
+        if next < good.len() && good[next] == i {
+            next += 1;
+            continue;
+        }
+        if !s[i].is_finite() {
+            continue;
+        }
+        values[i] = match (next.checked_sub(1).map(|j| good[j]), good.get(next)) {
+            (Some(a), Some(&b)) => {
+                values[a] + (values[b] - values[a]) * (s[i] - s[a]) / (s[b] - s[a])
+            }
+            (Some(a), None) => values[a],
+            (None, Some(&b)) => values[b],
+            (None, None) => unreachable!(),
+        };
+    }
+}
+
+impl FromStr for Imputation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Imputation::Reject),
+            "neighbours" => Ok(Imputation::Neighbours),
+            "last-frame" => Ok(Imputation::LastFrame),
+            _ => Err(format!("unknown imputation policy: {}", s)),
+        }
+    }
+}
+
+impl Display for Imputation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Imputation::Reject => "reject",
+            Imputation::Neighbours => "neighbours",
+            Imputation::LastFrame => "last-frame",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Imputation;
+
+    const NAN: f64 = f64::NAN;
+
+    #[test]
+    fn neighbours() {
+        let mut data = [
+            (0., NAN, 0.1),
+            (1., 0.2, NAN),
+            (3., NAN, 0.3),
+            (4., 0.5, 0.4),
+            (5., 0.6, NAN),
+        ];
+        assert_eq!(
+            vec![0, 1, 2, 4],
+            Imputation::Neighbours.apply(&mut data, None)
+        );
+        let expected = [
+            (0., 0.2, 0.1),
+            (1., 0.2, 0.1 + 0.2 / 3.),
+            (3., 0.4, 0.3),
+            (4., 0.5, 0.4),
+            (5., 0.6, 0.4),
+        ];
+        for (p, q) in data.iter().zip(expected.iter()) {
+            assert!(
+                (p.1 - q.1).abs() < 1e-12 && (p.2 - q.2).abs() < 1e-12,
+                "{:?}",
+                p
+            );
+        }
+
+        // nothing to fill from.
+        let mut data = [(0., NAN, 0.), (1., NAN, 0.)];
+        assert!(Imputation::Neighbours.apply(&mut data, None).is_empty());
+        assert!(data[0].1.is_nan());
+    }
+
+    #[test]
+    fn last_frame() {
+        let last = [(0., 0.1, 0.1), (1., 0.2, 0.2), (2., 0.3, 0.3)];
+        let mut data = [(0., 0., 0.), (1., NAN, 0.5), (2., 0.4, 0.4)];
+        assert_eq!(vec![1], Imputation::LastFrame.apply(&mut data, Some(&last)));
+        assert_eq!((1., 0.2, 0.5), data[1]);
+
+        // stations moved or changed, or no last frame, reject.
+        let mut data = [(0., 0., 0.), (1.5, NAN, 0.5), (2., 0.4, 0.4)];
+        assert!(Imputation::LastFrame
+            .apply(&mut data, Some(&last))
+            .is_empty());
+        assert!(data[1].1.is_nan());
+        let mut data = [(0., 0., 0.), (1., NAN, 0.5)];
+        assert!(Imputation::LastFrame
+            .apply(&mut data, Some(&last))
+            .is_empty());
+        assert!(Imputation::LastFrame.apply(&mut data, None).is_empty());
+        assert!(data[1].1.is_nan());
+    }
+
+    #[test]
+    fn reject() {
+        let mut data = [(0., 0., 0.), (1., NAN, 0.)];
+        assert!(Imputation::Reject.apply(&mut data, None).is_empty());
+        assert!(data[1].1.is_nan());
+
+        assert_eq!(Ok(Imputation::LastFrame), "last-frame".parse());
+        assert!("median".parse::<Imputation>().is_err());
+        assert_eq!("neighbours", Imputation::Neighbours.to_string());
+    }
+}
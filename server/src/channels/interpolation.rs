@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Interpolation scheme of curvature between sensor stations.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Scheme {
+    /// Piecewise linear.
+    #[default]
+    Linear,
+
+    /// Cubic Hermite with chord tangents, (k[i + 1] - k[i - 1]) / (s[i + 1] - s[i - 1]).
+    CatmullRom,
+
+    /// Cubic Hermite with three-point (parabolic) tangents.
+    CubicHermite,
+
+    /// C2 cubic spline with zero second derivative at both ends.
+    NaturalCubic,
+
+    /// Akima spline, robust against outliers.
+    Akima,
+
+    /// Monotone piecewise cubic (Fritsch-Carlson), no overshoot between stations.
+    Monotone,
+}
+
+/// A piecewise interpolant of (s, k) keys.
+pub struct Interpolant {
+    s: Vec<f64>,
+    k: Vec<f64>,
+
+    // tangents of each key; None for linear interpolation.
+    tangents: Option<Vec<f64>>,
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Scheme::Linear),
+            "catmull-rom" => Ok(Scheme::CatmullRom),
+            "cubic-hermite" => Ok(Scheme::CubicHermite),
+            "natural-cubic" => Ok(Scheme::NaturalCubic),
+            "akima" => Ok(Scheme::Akima),
+            "monotone" => Ok(Scheme::Monotone),
+            _ => Err(format!("unknown interpolation scheme: {}", s)),
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Scheme::Linear => "linear",
+            Scheme::CatmullRom => "catmull-rom",
+            Scheme::CubicHermite => "cubic-hermite",
+            Scheme::NaturalCubic => "natural-cubic",
+            Scheme::Akima => "akima",
+            Scheme::Monotone => "monotone",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Interpolant {
+    /// Build interpolant from keys sorted by s.
+    pub fn new(keys: impl Iterator<Item = (f64, f64)>, scheme: Scheme) -> Self {
+        let (s, k): (Vec<_>, Vec<_>) = keys.unzip();
+        let tangents = match scheme {
+            Scheme::Linear => None,
+            _ if s.len() < 3 => Some(vec![secant(&s, &k, 0); s.len()]),
+            Scheme::CatmullRom => Some(catmull_rom(&s, &k)),
+            Scheme::CubicHermite => Some(cubic_hermite(&s, &k)),
+            Scheme::NaturalCubic => Some(natural_cubic(&s, &k)),
+            Scheme::Akima => Some(akima(&s, &k)),
+            Scheme::Monotone => Some(monotone(&s, &k)),
+        };
+        Self { s, k, tangents }
+    }
+
+    /// Sample at `x`; return None if x is out of range of keys.
+    pub fn sample(&self, x: f64) -> Option<f64> {
+        let n = self.s.len();
+        if n == 0 || !(x >= self.s[0] && x <= self.s[n - 1]) {
+            return None;
+        }
+        if n == 1 {
+            return Some(self.k[0]);
+        }
+        let i = match self
+            .s
+            .binary_search_by(|v| v.partial_cmp(&x).unwrap_or(Ordering::Greater))
+        {
+            Ok(i) => i.min(n - 2),
+            Err(i) => i - 1,
+        };
+        let h = self.s[i + 1] - self.s[i];
+        let t = (x - self.s[i]) / h;
+        let (k0, k1) = (self.k[i], self.k[i + 1]);
+        match self.tangents {
+            None => Some(k0 + t * (k1 - k0)),
+            Some(ref m) => {
+                // cubic hermite basis
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let h00 = 2. * t3 - 3. * t2 + 1.;
+                let h10 = t3 - 2. * t2 + t;
+                let h01 = -2. * t3 + 3. * t2;
+                let h11 = t3 - t2;
+                Some(h00 * k0 + h10 * h * m[i] + h01 * k1 + h11 * h * m[i + 1])
+            }
+        }
+    }
+}
+
+/// Slope of segment i.
+fn secant(s: &[f64], k: &[f64], i: usize) -> f64 {
+    if s.len() < 2 {
+        0.
+    } else {
+        (k[i + 1] - k[i]) / (s[i + 1] - s[i])
+    }
+}
+
+/// Tangent at an end key, chosen to make the second derivative vanish there.
+fn free_end(secant: f64, neighbour: f64) -> f64 {
+    (3. * secant - neighbour) / 2.
+}
+
+/// Three-point tangent at an end key, from the parabola through the three keys at that end.
+fn parabolic_end(h0: f64, h1: f64, d0: f64, d1: f64) -> f64 {
+    ((2. * h0 + h1) * d0 - h0 * d1) / (h0 + h1)
+}
+
+fn catmull_rom(s: &[f64], k: &[f64]) -> Vec<f64> {
+    let n = s.len();
+    let mut m = vec![0.; n];
+    for i in 1..n - 1 {
+        m[i] = (k[i + 1] - k[i - 1]) / (s[i + 1] - s[i - 1]);
+    }
+    m[0] = free_end(secant(s, k, 0), m[1]);
+    m[n - 1] = free_end(secant(s, k, n - 2), m[n - 2]);
+    m
+}
+
+fn cubic_hermite(s: &[f64], k: &[f64]) -> Vec<f64> {
+    let n = s.len();
+    let h = (0..n - 1).map(|i| s[i + 1] - s[i]).collect::<Vec<_>>();
+    let d = (0..n - 1).map(|i| secant(s, k, i)).collect::<Vec<_>>();
+    let mut m = vec![0.; n];
+    for i in 1..n - 1 {
+        m[i] = (h[i] * d[i - 1] + h[i - 1] * d[i]) / (h[i - 1] + h[i]);
+    }
+    m[0] = parabolic_end(h[0], h[1], d[0], d[1]);
+    m[n - 1] = parabolic_end(h[n - 2], h[n - 3], d[n - 2], d[n - 3]);
+    m
+}
+
+fn natural_cubic(s: &[f64], k: &[f64]) -> Vec<f64> {
+    let n = s.len();
+    let h = (0..n - 1).map(|i| s[i + 1] - s[i]).collect::<Vec<_>>();
+    let d = (0..n - 1).map(|i| secant(s, k, i)).collect::<Vec<_>>();
+
+    // solve tridiagonal system of second derivatives, M[0] = M[n - 1] = 0 (Thomas algorithm).
+    let mut diag = vec![1.; n];
+    let mut rhs = vec![0.; n];
+    let mut upper = vec![0.; n];
+    for i in 1..n - 1 {
+        let lower = h[i - 1];
+        diag[i] = 2. * (h[i - 1] + h[i]) - lower * upper[i - 1];
+        upper[i] = h[i] / diag[i];
+        rhs[i] = (6. * (d[i] - d[i - 1]) - lower * rhs[i - 1]) / diag[i];
+    }
+    let mut second = vec![0.; n];
+    for i in (1..n - 1).rev() {
+        second[i] = rhs[i] - upper[i] * second[i + 1];
+    }
+
+    // convert second derivatives to tangents.
+    let mut m = (0..n - 1)
+        .map(|i| d[i] - h[i] * (2. * second[i] + second[i + 1]) / 6.)
+        .collect::<Vec<_>>();
+    m.push(d[n - 2] + h[n - 2] * (second[n - 2] + 2. * second[n - 1]) / 6.);
+    m
+}
+
+fn akima(s: &[f64], k: &[f64]) -> Vec<f64> {
+    let n = s.len();
+
+    // slopes extended by two virtual segments at each end.
+    let mut d = Vec::with_capacity(n + 3);
+    d.push(0.);
+    d.push(0.);
+    d.extend((0..n - 1).map(|i| secant(s, k, i)));
+    d[1] = 2. * d[2] - d[3];
+    d[0] = 2. * d[1] - d[2];
+    let last = d.len() - 1;
+    d.push(2. * d[last] - d[last - 1]);
+    d.push(2. * d[last + 1] - d[last]);
+
+    (0..n)
+        .map(|i| {
+            // slopes d[i - 2], d[i - 1], d[i], d[i + 1] in shifted index.
+            let w1 = (d[i + 3] - d[i + 2]).abs();
+            let w2 = (d[i + 1] - d[i]).abs();
+            if w1 + w2 == 0. {
+                (d[i + 1] + d[i + 2]) / 2.
+            } else {
+                (w1 * d[i + 1] + w2 * d[i + 2]) / (w1 + w2)
+            }
+        })
+        .collect()
+}
+
+fn monotone(s: &[f64], k: &[f64]) -> Vec<f64> {
+    let n = s.len();
+    let h = (0..n - 1).map(|i| s[i + 1] - s[i]).collect::<Vec<_>>();
+    let d = (0..n - 1).map(|i| secant(s, k, i)).collect::<Vec<_>>();
+    let mut m = vec![0.; n];
+    for i in 1..n - 1 {
+        if d[i - 1] * d[i] > 0. {
+            // weighted harmonic mean
+            let w1 = 2. * h[i] + h[i - 1];
+            let w2 = h[i] + 2. * h[i - 1];
+            m[i] = (w1 + w2) / (w1 / d[i - 1] + w2 / d[i]);
+        }
+    }
+
+    let end = |h0: f64, h1: f64, d0: f64, d1: f64| {
+        let m = parabolic_end(h0, h1, d0, d1);
+        if m * d0 <= 0. {
+            0.
+        } else if d0 * d1 <= 0. && m.abs() > 3. * d0.abs() {
+            3. * d0
+        } else {
+            m
+        }
+    };
+    m[0] = end(h[0], h[1], d[0], d[1]);
+    m[n - 1] = end(h[n - 2], h[n - 3], d[n - 2], d[n - 3]);
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Interpolant, Scheme};
+
+    const SCHEMES: [Scheme; 6] = [
+        Scheme::Linear,
+        Scheme::CatmullRom,
+        Scheme::CubicHermite,
+        Scheme::NaturalCubic,
+        Scheme::Akima,
+        Scheme::Monotone,
+    ];
+
+    const KEYS: [(f64, f64); 7] = [
+        (0., 0.),
+        (4.66, 0.21),
+        (9.36, 0.27),
+        (14.82, 0.086),
+        (19.72, -0.0093),
+        (24.74, -0.091),
+        (29.95, -0.079),
+    ];
+
+    #[test]
+    fn pass_through_keys() {
+        for scheme in SCHEMES.iter() {
+            let interpolant = Interpolant::new(KEYS.iter().cloned(), *scheme);
+            for (s, k) in KEYS.iter() {
+                let sample = interpolant.sample(*s).unwrap();
+                assert!(
+                    (sample - k).abs() < 1e-12,
+                    "{}: {} != {}",
+                    scheme,
+                    sample,
+                    k
+                );
+            }
+            assert!(interpolant.sample(-0.01).is_none());
+            assert!(interpolant.sample(29.96).is_none());
+        }
+    }
+
+    #[test]
+    fn reproduce_line() {
+        // every scheme reproduces a straight line exactly, end segments included.
+        let keys = (0..6).map(|i| (i as f64 * 1.3, 2. * i as f64 * 1.3 + 1.));
+        for scheme in SCHEMES.iter() {
+            let interpolant = Interpolant::new(keys.clone(), *scheme);
+            for i in 0..50 {
+                let x = i as f64 * 0.13;
+                let sample = interpolant.sample(x).unwrap();
+                assert!((sample - (2. * x + 1.)).abs() < 1e-9, "{}", scheme);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_end_segments() {
+        // samples of sin should be reproduced far better by cubic schemes than linear.
+        let keys = (0..9)
+            .map(|i| i as f64 * std::f64::consts::PI / 8.)
+            .map(|x| (x, x.sin()))
+            .collect::<Vec<_>>();
+        let max_error = |scheme| {
+            let interpolant = Interpolant::new(keys.iter().cloned(), scheme);
+            (0..=100)
+                .map(|i| i as f64 * std::f64::consts::PI / 100.)
+                .map(|x| (interpolant.sample(x).unwrap() - x.sin()).abs())
+                .fold(0., f64::max)
+        };
+        let linear = max_error(Scheme::Linear);
+        for scheme in SCHEMES[1..].iter() {
+            assert!(max_error(*scheme) < linear, "{}", scheme);
+        }
+    }
+
+    #[test]
+    fn monotone_no_overshoot() {
+        let keys = [(0., 0.), (1., 0.), (2., 1.), (3., 1.), (4., 1.)];
+        let interpolant = Interpolant::new(keys.iter().cloned(), Scheme::Monotone);
+        for i in 0..=400 {
+            let sample = interpolant.sample(i as f64 * 0.01).unwrap();
+            assert!((0. ..=1.).contains(&sample), "{}", sample);
+        }
+    }
+
+    #[test]
+    fn parse_scheme() {
+        for scheme in SCHEMES.iter() {
+            assert_eq!(*scheme, scheme.to_string().parse().unwrap());
+        }
+        assert!("cubic".parse::<Scheme>().is_err());
+    }
+}
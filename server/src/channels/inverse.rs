@@ -0,0 +1,296 @@
+use super::error::ReconstructError;
+use nalgebra::{Matrix3, Rotation3, Unit, Vector3};
+
+/// Segments shorter than this are merged into their neighbours.
+const MIN_SEGMENT: f64 = 1e-12;
+
+/// Curvature samples (s, ka, kb) a sensor would report along a polyline,
+/// at arc length `stations` from the first point.
+///
+/// `ri` maps absolute coordinate to relative coordinate at the first point, the same as
+/// reconstructors; if its z axis is not the first tangent, it is rotated to the tangent first.
+/// The sensor frame is transported along the curve without twist, like `frenet_reconstruct`.
+pub fn curvature_samples(
+    points: &[Vector3<f64>],
+    ri: Matrix3<f64>,
+    stations: &[f64],
+) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+    if points.is_empty() {
+        return Err(ReconstructError::Empty);
+    }
+    if let Some(index) = points.iter().position(|p| !p.iter().all(|v| v.is_finite())) {
+        return Err(ReconstructError::NonFinite { index });
+    }
+    if !(ri.iter().all(|v| v.is_finite()) && ri.determinant() > 1e-9) {
+        return Err(ReconstructError::SingularRotation);
+    }
+
+    // tangents and lengths of segments, without degenerate ones.
+    let mut tangents = Vec::with_capacity(points.len());
+    let mut lengths = Vec::with_capacity(points.len());
+    for window in points.windows(2) {
+        let chord = window[1] - window[0];
+        let length = chord.norm();
+        if length > MIN_SEGMENT {
+            tangents.push(chord / length);
+            lengths.push(length);
+        }
+    }
+    if tangents.is_empty() {
+        return Err(ReconstructError::TooFewSamples {
+            required: 2,
+            found: 1,
+        });
+    }
+
+    // frame of the first segment, columns are axes of relative coordinate.
+    let mut frame = Rotation3::from_matrix_unchecked(ri.transpose());
+    frame.renormalize();
+    frame = minimal_rotation(&(frame * Vector3::z()), &tangents[0], 0)? * frame;
+
+    // (s, ka, kb) at each vertex.
+    let mut vertices = Vec::with_capacity(tangents.len() + 1);
+    vertices.push((0., 0., 0.));
+    let mut s = lengths[0];
+    for k in 1..tangents.len() {
+        let (last, tangent) = (&tangents[k - 1], &tangents[k]);
+        let rotation = minimal_rotation(last, tangent, k)?;
+        let angle = rotation.angle();
+        let curvature = angle / ((lengths[k - 1] + lengths[k]) / 2.);
+
+        // curvature vector toward the center, in frame halfway through the turn.
+        let half = match rotation.axis() {
+            Some(axis) => Rotation3::from_axis_angle(&axis, angle / 2.),
+            None => Rotation3::identity(),
+        };
+        let middle = half * frame;
+        let normal = match rotation.axis() {
+            Some(axis) => axis.cross(&(half * last)) * curvature,
+            None => Vector3::zeros(),
+        };
+        // the curve bends toward -(ka, kb).
+        vertices.push((
+            s,
+            -normal.dot(&(middle * Vector3::x())),
+            -normal.dot(&(middle * Vector3::y())),
+        ));
+
+        frame = rotation * frame;
+        s += lengths[k];
+    }
+    vertices.push((s, 0., 0.));
+
+    // curvature of ends is extrapolated from their neighbours.
+    let n = vertices.len();
+    if n > 3 {
+        vertices[0] = extrapolate(vertices[1], vertices[2], 0.);
+        vertices[n - 1] = extrapolate(vertices[n - 2], vertices[n - 3], s);
+    } else if n == 3 {
+        vertices[0] = (0., vertices[1].1, vertices[1].2);
+        vertices[2] = (s, vertices[1].1, vertices[1].2);
+    }
+
+    stations
+        .iter()
+        .map(|station| sample(&vertices, *station))
+        .collect()
+}
+
+/// Curvature samples along a parametric curve `curve(t)`, t in `range`,
+/// approximated by a polyline of `resolution` segments.
+pub fn parametric_samples(
+    curve: impl Fn(f64) -> Vector3<f64>,
+    (start, end): (f64, f64),
+    resolution: usize,
+    ri: Matrix3<f64>,
+    stations: &[f64],
+) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+    let points = (0..=resolution.max(1))
+        .map(|i| curve(start + (end - start) * i as f64 / resolution.max(1) as f64))
+        .collect::<Vec<_>>();
+    curvature_samples(&points, ri, stations)
+}
+
+/// The smallest rotation turning unit vector `from` to unit vector `to`.
+fn minimal_rotation(
+    from: &Vector3<f64>,
+    to: &Vector3<f64>,
+    index: usize,
+) -> Result<Rotation3<f64>, ReconstructError> {
+    let cross = from.cross(to);
+    let cos = from.dot(to);
+    match Unit::try_new(cross, MIN_SEGMENT) {
+        Some(axis) => Ok(Rotation3::from_axis_angle(&axis, cross.norm().atan2(cos))),
+        None if cos > 0. => Ok(Rotation3::identity()),
+        // folds back, bending direction is undefined.
+        None => Err(ReconstructError::Cusp { index }),
+    }
+}
+
+/// Linear extrapolate (ka, kb) at s from the nearer vertex `a` and the farther vertex `b`.
+fn extrapolate(a: (f64, f64, f64), b: (f64, f64, f64), s: f64) -> (f64, f64, f64) {
+    let t = (s - a.0) / (a.0 - b.0);
+    (s, a.1 + (a.1 - b.1) * t, a.2 + (a.2 - b.2) * t)
+}
+
+/// Linear interpolate (ka, kb) of vertices at s.
+fn sample(vertices: &[(f64, f64, f64)], s: f64) -> Result<(f64, f64, f64), ReconstructError> {
+    let length = vertices[vertices.len() - 1].0;
+    // tolerate rounding error of accumulated length.
+    if !(s >= 0. && s <= length * (1. + 1e-9)) {
+        return Err(ReconstructError::OutOfRange(s));
+    }
+    let s = s.min(length);
+    let index = match vertices.binary_search_by(|v| v.0.partial_cmp(&s).unwrap()) {
+        Ok(index) => return Ok((s, vertices[index].1, vertices[index].2)),
+        Err(index) => index,
+    };
+    let (s0, a0, b0) = vertices[index - 1];
+    let (s1, a1, b1) = vertices[index];
+    let t = (s - s0) / (s1 - s0);
+    Ok((s, a0 + (a1 - a0) * t, b0 + (b1 - b0) * t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{curvature_samples, parametric_samples};
+    use crate::channels::curvature_splines::PointSlice;
+    use crate::channels::error::ReconstructError;
+    use crate::channels::interpolation::Scheme;
+    use crate::channels::metrics::helix;
+    use crate::curve::Point;
+    use nalgebra::{Matrix3, Vector3};
+    use num::{One, Zero};
+
+    const DATA: [(f64, f64, f64); 7] = [
+        (0., 0., 0.),
+        (4.66, 0.21, 0.1),
+        (9.36, 0.27, -0.05),
+        (14.82, 0.086, 0.),
+        (19.72, -0.0093, -0.2),
+        (24.74, -0.091, 0.),
+        (29.95, -0.079, 0.03),
+    ];
+
+    fn position(p: &Point) -> Vector3<f64> {
+        Vector3::new(p.x as f64, p.y as f64, p.z as f64)
+    }
+
+    /// Reconstruct with frenet, prepending the base point.
+    fn reconstruct(
+        data: &[(f64, f64, f64)],
+        ds: f64,
+        scheme: Scheme,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Vec<Vector3<f64>> {
+        let mut points = vec![ai];
+        points.extend(
+            data.interpolate_with(ds, scheme)
+                .unwrap()
+                .frenet_reconstruct(ai, ri)
+                .unwrap()
+                .iter()
+                .map(position),
+        );
+        points
+    }
+
+    #[test]
+    fn round_trip_samples() {
+        let ai = Vector3::new(1., -2., 3.);
+        let ri = Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.);
+        let points = reconstruct(&DATA, 0.01, Scheme::Linear, ai, ri);
+        // points are f32, use longer chords to suppress rounding noise in curvature.
+        let points = points.iter().step_by(10).cloned().collect::<Vec<_>>();
+        // chords are a little shorter than arcs.
+        let length: f64 = points.windows(2).map(|w| (w[1] - w[0]).norm()).sum();
+        let stations = DATA
+            .iter()
+            .map(|(s, _, _)| s.min(length))
+            .collect::<Vec<_>>();
+        let samples = curvature_samples(&points, ri, &stations).unwrap();
+        for (p, q) in samples.iter().zip(DATA.iter()) {
+            assert!(
+                (p.1 - q.1).abs() < 2e-3 && (p.2 - q.2).abs() < 2e-3,
+                "{:?} {:?}",
+                p,
+                q
+            );
+        }
+
+        // and back to the same curve, from dense stations.
+        let stations = (0..)
+            .map(|i| i as f64 * 0.1)
+            .take_while(|s| *s <= length)
+            .collect::<Vec<_>>();
+        let samples = curvature_samples(&points, ri, &stations).unwrap();
+        let curve = reconstruct(&samples, 0.01, Scheme::Linear, ai, ri);
+        let max_error = curve
+            .iter()
+            .step_by(10)
+            .zip(points.iter())
+            .map(|(p, q)| (p - q).norm())
+            .fold(0., f64::max);
+        assert!(max_error < 2e-2, "{}", max_error);
+    }
+
+    #[test]
+    fn round_trip_helix() {
+        // helix starts from tangent z and normal -x, like `torsion_helix`.
+        let (kappa, tau) = (0.5f64, 0.2f64);
+        let helix = helix(kappa, tau);
+
+        let stations = (0..=40).map(|i| i as f64 * 0.5).collect::<Vec<_>>();
+        let samples = parametric_samples(helix, (0., 20.5), 4100, One::one(), &stations).unwrap();
+        for (s, ka, kb) in samples.iter() {
+            // curvature vector rotates at rate of torsion in the twist-free frame.
+            let (ka_exact, kb_exact) = (kappa * (tau * s).cos(), kappa * (tau * s).sin());
+            assert!((ka - ka_exact).abs() < 1e-3, "{}: {}", s, ka);
+            assert!((kb - kb_exact).abs() < 1e-3, "{}: {}", s, kb);
+        }
+
+        let curve = reconstruct(
+            &samples,
+            0.01,
+            Scheme::NaturalCubic,
+            Zero::zero(),
+            One::one(),
+        );
+        let max_error = curve
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p - helix(i as f64 * 0.01)).norm())
+            .fold(0., f64::max);
+        assert!(max_error < 1e-2, "{}", max_error);
+    }
+
+    #[test]
+    fn reject() {
+        let line = [Vector3::zeros(), Vector3::z(), Vector3::z() * 2.];
+        assert_eq!(
+            Some(ReconstructError::OutOfRange(3.)),
+            curvature_samples(&line, One::one(), &[1., 3.]).err()
+        );
+        assert_eq!(
+            Ok(vec![(0.5, 0., 0.)]),
+            curvature_samples(&line, One::one(), &[0.5])
+        );
+        assert_eq!(
+            Some(ReconstructError::Cusp { index: 1 }),
+            curvature_samples(
+                &[Vector3::zeros(), Vector3::z(), Vector3::zeros()],
+                One::one(),
+                &[0.]
+            )
+            .err()
+        );
+        assert_eq!(
+            Some(ReconstructError::TooFewSamples {
+                required: 2,
+                found: 1
+            }),
+            curvature_samples(&[Vector3::zeros(), Vector3::zeros()], One::one(), &[0.]).err()
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use super::error::ReconstructError;
+use crate::curve::Point;
+use nalgebra::Vector3;
+
+/// Accuracy of a reconstructed curve against a reference curve, in unit of s.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Metrics {
+    /// distance between tips.
+    pub tip_error: f64,
+
+    /// angle between tip tangents, in radians.
+    pub tip_angle_error: f64,
+
+    /// root mean square of distances between points at the same arc length.
+    pub rms_error: f64,
+
+    /// maximum of distances between points at the same arc length.
+    pub max_error: f64,
+
+    /// discrete Hausdorff distance.
+    pub hausdorff: f64,
+
+    /// discrete Frechet distance.
+    pub frechet: f64,
+
+    /// length of curve minus length of reference.
+    pub length_drift: f64,
+}
+
+/// Absolute coordinates of points.
+pub fn positions(points: &[Point]) -> Vec<Vector3<f64>> {
+    points
+        .iter()
+        .map(|p| Vector3::new(p.x as f64, p.y as f64, p.z as f64))
+        .collect()
+}
+
+/// Evaluate a polyline against a reference polyline, both starting at the base.
+///
+/// Point-wise errors compare each point of curve with the reference point at the same arc length,
+/// so two polylines may be sampled differently.
+pub fn evaluate(
+    curve: &[Vector3<f64>],
+    reference: &[Vector3<f64>],
+) -> Result<Metrics, ReconstructError> {
+    let errors = errors(curve, reference)?
+        .into_iter()
+        .map(|(_, error)| error)
+        .collect::<Vec<_>>();
+    let curve_s = arc_lengths(curve);
+    let reference_s = arc_lengths(reference);
+    let tangent = |line: &[Vector3<f64>]| (line[line.len() - 1] - line[line.len() - 2]).normalize();
+    let cos = tangent(curve).dot(&tangent(reference));
+    let sin = tangent(curve).cross(&tangent(reference)).norm();
+
+    Ok(Metrics {
+        tip_error: (curve[curve.len() - 1] - reference[reference.len() - 1]).norm(),
+        tip_angle_error: sin.atan2(cos),
+        rms_error: (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt(),
+        max_error: errors.iter().cloned().fold(0., f64::max),
+        hausdorff: directed_hausdorff(curve, reference).max(directed_hausdorff(reference, curve)),
+        frechet: frechet(curve, reference),
+        length_drift: curve_s[curve_s.len() - 1] - reference_s[reference_s.len() - 1],
+    })
+}
+
+/// Arc length of each point of a polyline, with its distance from the point of reference
+/// at the same arc length, both starting at the base.
+pub fn errors(
+    curve: &[Vector3<f64>],
+    reference: &[Vector3<f64>],
+) -> Result<Vec<(f64, f64)>, ReconstructError> {
+    check(curve)?;
+    check(reference)?;
+    let reference_s = arc_lengths(reference);
+    Ok(arc_lengths(curve)
+        .into_iter()
+        .zip(curve.iter())
+        .map(|(s, p)| (s, (p - resample(reference, &reference_s, s)).norm()))
+        .collect())
+}
+
+/// Analytic helix of curvature `kappa` and torsion `tau` at arc length s,
+/// starting at the origin from tangent z, normal -x and binormal -y.
+#[cfg(test)]
+pub fn helix(kappa: f64, tau: f64) -> impl Fn(f64) -> Vector3<f64> + Copy {
+    let omega = (kappa.powi(2) + tau.powi(2)).sqrt();
+    let (tangent, normal, binormal) = (Vector3::z(), -Vector3::x(), -Vector3::y());
+    let axis = (tangent * tau + binormal * kappa) / omega;
+    let u = (tangent * omega - axis * tau) / kappa;
+    move |s| {
+        axis * (tau / omega * s)
+            + (u * (omega * s).sin() + normal * (1. - (omega * s).cos())) * (kappa / omega.powi(2))
+    }
+}
+
+/// Check polyline has at least two finite points.
+fn check(line: &[Vector3<f64>]) -> Result<(), ReconstructError> {
+    match line.len() {
+        0 => return Err(ReconstructError::Empty),
+        1 => {
+            return Err(ReconstructError::TooFewSamples {
+                required: 2,
+                found: 1,
+            })
+        }
+        _ => (),
+    }
+    match line.iter().position(|p| !p.iter().all(|v| v.is_finite())) {
+        Some(index) => Err(ReconstructError::NonFinite { index }),
+        None => Ok(()),
+    }
+}
+
+/// Cumulative arc length of each point.
+fn arc_lengths(line: &[Vector3<f64>]) -> Vec<f64> {
+    let mut s = 0.;
+    let mut lengths = Vec::with_capacity(line.len());
+    lengths.push(0.);
+    for window in line.windows(2) {
+        s += (window[1] - window[0]).norm();
+        lengths.push(s);
+    }
+    lengths
+}
+
+/// Point at arc length s, clamped to ends.
+fn resample(line: &[Vector3<f64>], lengths: &[f64], s: f64) -> Vector3<f64> {
+    let index = lengths.partition_point(|length| *length < s);
+    if index == 0 {
+        line[0]
+    } else if index == line.len() {
+        line[line.len() - 1]
+    } else {
+        let (s0, s1) = (lengths[index - 1], lengths[index]);
+        let t = (s - s0) / (s1 - s0);
+        line[index - 1] * (1. - t) + line[index] * t
+    }
+}
+
+/// Largest distance from a point of `from` to its nearest point of `to`.
+fn directed_hausdorff(from: &[Vector3<f64>], to: &[Vector3<f64>]) -> f64 {
+    from.iter()
+        .map(|p| {
+            to.iter()
+                .map(|q| (p - q).norm_squared())
+                .fold(f64::INFINITY, f64::min)
+        })
+        .fold(0., f64::max)
+        .sqrt()
+}
+
+/// Discrete Frechet distance by dynamic programming, in O(nm) time and O(m) space.
+fn frechet(p: &[Vector3<f64>], q: &[Vector3<f64>]) -> f64 {
+    let mut row = vec![0.; q.len()];
+    for (i, a) in p.iter().enumerate() {
+        let mut diagonal = 0.;
+        for (j, b) in q.iter().enumerate() {
+            let distance = (a - b).norm();
+            let coupling = match (i, j) {
+                (0, 0) => distance,
+                (0, _) => row[j - 1],
+                (_, 0) => row[0],
+                _ => f64::min(diagonal, row[j].min(row[j - 1])),
+            }
+            .max(distance);
+            diagonal = row[j];
+            row[j] = coupling;
+        }
+    }
+    row[q.len() - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{errors, evaluate, frechet, helix};
+    use crate::channels::error::ReconstructError;
+    use nalgebra::Vector3;
+
+    fn line(points: &[(f64, f64, f64)]) -> Vec<Vector3<f64>> {
+        points
+            .iter()
+            .map(|(x, y, z)| Vector3::new(*x, *y, *z))
+            .collect()
+    }
+
+    #[test]
+    fn identical() {
+        let curve = line(&[(0., 0., 0.), (0., 0., 1.), (0., 1., 2.)]);
+        let metrics = evaluate(&curve, &curve).unwrap();
+        assert_eq!(0., metrics.tip_error);
+        assert_eq!(0., metrics.tip_angle_error);
+        assert_eq!(0., metrics.max_error);
+        assert_eq!(0., metrics.hausdorff);
+        assert_eq!(0., metrics.frechet);
+        assert_eq!(0., metrics.length_drift);
+    }
+
+    #[test]
+    fn known_values() {
+        // straight rod and the same rod bent by 90 degrees at its middle.
+        let reference = line(&[(0., 0., 0.), (0., 0., 1.), (0., 0., 2.)]);
+        let curve = line(&[(0., 0., 0.), (0., 0., 1.), (1., 0., 1.)]);
+        let metrics = evaluate(&curve, &reference).unwrap();
+        assert!((metrics.tip_error - 2f64.sqrt()).abs() < 1e-12);
+        assert!((metrics.tip_angle_error - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert!((metrics.rms_error - (2f64 / 3.).sqrt()).abs() < 1e-12);
+        assert!((metrics.max_error - 2f64.sqrt()).abs() < 1e-12);
+        assert!((metrics.hausdorff - 1.).abs() < 1e-12);
+        assert!((metrics.frechet - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(0., metrics.length_drift);
+
+        // a longer curve, sampled differently.
+        let curve = line(&[(0., 0., 0.), (0., 0., 0.5), (0., 0., 1.), (0., 0., 2.5)]);
+        let metrics = evaluate(&curve, &reference).unwrap();
+        assert!((metrics.tip_error - 0.5).abs() < 1e-12);
+        assert!((metrics.max_error - 0.5).abs() < 1e-12);
+        assert!((metrics.length_drift - 0.5).abs() < 1e-12);
+
+        // frechet distance is not less than hausdorff distance, e.g. on a reversed path.
+        let p = line(&[(0., 0., 0.), (1., 0., 0.), (2., 0., 0.)]);
+        let q = line(&[(2., 0., 0.), (1., 0., 0.), (0., 0., 0.)]);
+        assert_eq!(2., frechet(&p, &q));
+
+        let errors = errors(&curve, &reference).unwrap();
+        assert_eq!(vec![(0., 0.), (0.5, 0.), (1., 0.), (2.5, 0.5)], errors);
+    }
+
+    #[test]
+    fn analytic_helix() {
+        let (kappa, tau) = (0.5, 0.2);
+        let helix = helix(kappa, tau);
+        assert_eq!(Vector3::zeros(), helix(0.));
+        // unit speed, with curvature kappa.
+        let ds = 1e-4;
+        for s in [0., 1., 5.].iter() {
+            let (a, b, c) = (helix(*s), helix(s + ds), helix(s + 2. * ds));
+            assert!(((b - a).norm() / ds - 1.).abs() < 1e-6);
+            assert!(((c - b * 2. + a).norm() / ds.powi(2) - kappa).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn reject() {
+        let curve = line(&[(0., 0., 0.), (0., 0., 1.)]);
+        assert_eq!(
+            Some(ReconstructError::TooFewSamples {
+                required: 2,
+                found: 1
+            }),
+            evaluate(&curve[..1], &curve).err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonFinite { index: 1 }),
+            evaluate(&curve, &line(&[(0., 0., 0.), (0., f64::NAN, 1.)])).err()
+        );
+    }
+}
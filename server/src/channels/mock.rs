@@ -1,15 +1,30 @@
-use super::curvature_splines::PointSlice;
-use super::SyncChannel;
+use super::{ChannelConfig, Step, SyncChannel};
 use crate::curve::Curve;
-use libflate::deflate::Encoder;
-use num::{One, Zero};
+use nalgebra::{Matrix3, Vector3};
 use rand::Rng;
 use roa::websocket::Message;
-use std::io::Write;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const MAX_FPS: u64 = 60;
 
+/// Config of `random_channel` and `static_channel`.
+pub fn static_config() -> ChannelConfig {
+    ChannelConfig {
+        step: Step::Fixed(0.1),
+        ..Default::default()
+    }
+}
+
+/// Config of `cos_channel`.
+pub fn cos_config() -> ChannelConfig {
+    ChannelConfig {
+        step: Step::Fixed(0.05),
+        ai: Vector3::new(0., 0., 1.), // initialized coordinate
+        ri: Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
+        ..Default::default()
+    }
+}
+
 #[allow(dead_code)]
 pub fn random_channel(channel: SyncChannel) {
     async_std::task::spawn(async move {
@@ -23,23 +38,30 @@ pub fn random_channel(channel: SyncChannel) {
                     *i += rng.gen_range(-0.001, 0.001);
                 }
             }
-            let points = [
-                (0., 0., 0.),
-                (4.66, curvatures[0], 0.),
-                (9.36, curvatures[1], 0.),
-                (14.82, curvatures[2], 0.),
-                (19.72, curvatures[3], 0.),
-                (24.74, curvatures[4], 0.),
-                (29.95, curvatures[5], 0.),
-            ]
-            .interpolate(0.1)
-            .frenet_reconstruct(Zero::zero(), One::one())
-            .unwrap();
+            let points = channel
+                .config()
+                .reconstruct(&[
+                    (0., 0., 0.),
+                    (4.66, curvatures[0], 0.),
+                    (9.36, curvatures[1], 0.),
+                    (14.82, curvatures[2], 0.),
+                    (19.72, curvatures[3], 0.),
+                    (24.74, curvatures[4], 0.),
+                    (29.95, curvatures[5], 0.),
+                ])
+                .unwrap();
             let timestamp = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64;
-            let curve = Curve { timestamp, points };
+            let curve = Curve {
+                timestamp,
+                seq: None,
+                points,
+                envelope: None,
+                imputed: Vec::new(),
+                closure: None,
+            };
             let data = serde_json::to_string(&curve).unwrap();
             channel.broadcast(Message::Text(data)).await;
             let cost = start.elapsed().unwrap();
@@ -63,15 +85,19 @@ pub fn static_channel(channel: SyncChannel) {
             (29.95, -0.079, 0.),
         ];
         loop {
-            let points = data
-                .interpolate(0.1)
-                .frenet_reconstruct(Zero::zero(), One::one())
-                .unwrap();
+            let points = channel.config().reconstruct(&data).unwrap();
             let timestamp = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64;
-            let curve = Curve { timestamp, points };
+            let curve = Curve {
+                timestamp,
+                seq: None,
+                points,
+                envelope: None,
+                imputed: Vec::new(),
+                closure: None,
+            };
             let data = serde_json::to_string(&curve).unwrap();
             channel.broadcast(Message::Text(data)).await;
         }
@@ -79,7 +105,6 @@ pub fn static_channel(channel: SyncChannel) {
 }
 
 pub fn cos_channel(channel: SyncChannel) {
-    use nalgebra::{Matrix3, Vector3};
     use std::f64::consts::PI;
     /// Get curvature of this point.
     fn cos_curvature(x: f64) -> f64 {
@@ -105,7 +130,7 @@ pub fn cos_channel(channel: SyncChannel) {
             .collect::<Vec<_>>();
         loop {
             let start = SystemTime::now();
-            let points = init_x
+            let data = init_x
                 .iter()
                 .map(|x| {
                     (
@@ -114,22 +139,18 @@ pub fn cos_channel(channel: SyncChannel) {
                         cos_curvature(offset + x) / 2f64.sqrt(),
                     )
                 }) // get pair (<arc length>, <curvature>, 0.)
-                .collect::<Vec<_>>()
-                .interpolate(0.05) // linear interpolate; ds = 0.05.
-                .frenet_reconstruct(
-                    Vector3::new(0., 0., 1.),                          // initialized coordinate
-                    Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.), // initialized rotation matrix
-                )
-                .unwrap();
+                .collect::<Vec<_>>();
+            let points = channel.config().reconstruct(&data).unwrap();
             let timestamp = start.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
-            let curve = Curve { timestamp, points };
-            let mut encoder = Encoder::new(Vec::new());
-            encoder
-                .write_all(&serde_json::to_vec(&curve).unwrap())
-                .unwrap();
-            channel
-                .broadcast(Message::Binary(encoder.finish().into_result().unwrap()))
-                .await;
+            let curve = Curve {
+                timestamp,
+                seq: None,
+                points,
+                envelope: None,
+                imputed: Vec::new(),
+                closure: None,
+            };
+            channel.publish(&curve).await;
             let cost = start.elapsed().unwrap();
             if cost < min_period_ms {
                 async_std::task::sleep(min_period_ms - cost).await;
@@ -0,0 +1,769 @@
+use super::shape::Pose;
+use crate::curve::{Curve, MultiCurve, Point};
+use libflate::deflate::Encoder;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::str::FromStr;
+
+/// The latest version of upstream envelope and binary frame.
+pub const VERSION: u32 = 1;
+
+/// Leading bytes of a binary frame, which never start a JSON text.
+pub const MAGIC: [u8; 4] = *b"CROW";
+
+/// Size of the header of a binary frame, in bytes.
+pub const HEADER_SIZE: usize = 20;
+
+/// Flag of a binary frame: samples are f64 instead of f32.
+pub const F64: u8 = 0b001;
+
+/// Flag of a binary frame: `seq` of header is set.
+pub const SEQ: u8 = 0b010;
+
+/// Flag of a binary frame: `timestamp` of header is set.
+pub const TIMESTAMP: u8 = 0b100;
+
+/// Flag of a binary curve: quaternions of points follow positions.
+pub const FRAMES: u8 = 0b001;
+
+/// Parent of a segment of binary curve starting at the base of channel.
+pub const NO_PARENT: u32 = u32::MAX;
+
+/// Unit of length, of distance or of curvature as its inverse.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Length {
+    Metre,
+    Centimetre,
+    Millimetre,
+}
+
+/// Units of samples declared by source, as is if None.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    /// unit of distance along the device, e.g. `"mm"`.
+    #[serde(deserialize_with = "distance")]
+    pub distance: Option<Length>,
+
+    /// unit of curvature, e.g. `"1/m"`.
+    #[serde(deserialize_with = "curvature")]
+    pub curvature: Option<Length>,
+}
+
+/// Metadata of a frame, beyond its samples.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Header {
+    /// sequence number of the frame at source.
+    pub seq: Option<u64>,
+
+    /// time of the frame at sensor in milliseconds since unix epoch, default to time of arrival.
+    pub timestamp: Option<u64>,
+
+    /// base pose at the time of the frame.
+    pub pose: Option<Pose>,
+}
+
+/// A frame from source client, a versioned envelope or a bare array of samples (version 0).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub version: u32,
+    pub header: Header,
+    pub units: Units,
+
+    /// stations of the frame, or one array of stations per segment of a structure.
+    pub samples: Value,
+}
+
+/// A little-endian binary frame of samples `(s, ka, kb)`, missing values are NaN.
+///
+/// | offset | size  | field                                            |
+/// | ------ | ----- | ------------------------------------------------ |
+/// | 0      | 4     | magic `CROW`                                     |
+/// | 4      | 1     | version                                          |
+/// | 5      | 1     | flags, of `F64`, `SEQ` and `TIMESTAMP`           |
+/// | 6      | 2     | count of samples, u16                            |
+/// | 8      | 4     | seq, u32                                         |
+/// | 12     | 8     | timestamp in milliseconds since unix epoch, u64  |
+/// | 20     | 12/24 | each sample, three f32 or f64                    |
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryFrame {
+    pub header: Header,
+    pub samples: Vec<(f64, f64, f64)>,
+}
+
+/// Encoding of curves sent to a subscriber, chosen when it connects.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Encoding {
+    /// JSON compressed by raw deflate.
+    #[default]
+    Json,
+
+    /// Packed little-endian f32 buffers of points.
+    Binary,
+}
+
+/// A curve sent to subscribers, as JSON or as a binary curve.
+///
+/// A binary curve is little-endian, with 4-byte aligned f32 buffers:
+///
+/// | offset         | size   | field                                               |
+/// | -------------- | ------ | --------------------------------------------------- |
+/// | 0              | 4      | magic `CROW`                                        |
+/// | 4              | 1      | version                                             |
+/// | 5              | 1      | flags, of `FRAMES`                                  |
+/// | 6              | 2      | count of segments, u16                              |
+/// | 8              | 8      | timestamp in milliseconds since unix epoch, u64     |
+/// | 16             | 8 each | count of points and parent of each segment, u32     |
+/// | 16 + 8m        | 12n    | positions [x, y, z] of all points, f32              |
+/// | 16 + 8m + 12n  | 16n    | quaternions [x, y, z, w] of all points, f32         |
+pub trait Downstream: Serialize {
+    /// Milliseconds since unix epoch.
+    fn timestamp(&self) -> u64;
+
+    /// Points of each segment, with index of its parent.
+    fn segments(&self) -> Vec<(&[Point], Option<usize>)>;
+
+    /// Encode the curve as a binary curve.
+    fn to_binary(&self) -> Vec<u8> {
+        let segments = self.segments();
+        let points = || segments.iter().flat_map(|(points, _)| points.iter());
+        let count = points().count();
+        let frames = count > 0 && points().all(|point| point.frame.is_some());
+        let mut raw =
+            Vec::with_capacity(16 + 8 * segments.len() + count * if frames { 28 } else { 12 });
+        raw.extend_from_slice(&MAGIC);
+        raw.push(VERSION as u8);
+        raw.push(if frames { FRAMES } else { 0 });
+        raw.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+        raw.extend_from_slice(&self.timestamp().to_le_bytes());
+        for (points, parent) in segments.iter() {
+            raw.extend_from_slice(&(points.len() as u32).to_le_bytes());
+            let parent = parent.map_or(NO_PARENT, |parent| parent as u32);
+            raw.extend_from_slice(&parent.to_le_bytes());
+        }
+        for point in points() {
+            for value in [point.x, point.y, point.z].iter() {
+                raw.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        if frames {
+            for frame in points().filter_map(|point| point.frame) {
+                for value in frame.rotation().coords.iter() {
+                    raw.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        raw
+    }
+}
+
+/// Base pose in envelope, orientation as quaternion [w, x, y, z].
+#[derive(Deserialize)]
+struct BasePose {
+    position: [f64; 3],
+    orientation: [f64; 4],
+}
+
+/// Envelope of version 1, unknown fields are ignored.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    seq: Option<u64>,
+    #[serde(default)]
+    timestamp: Option<u64>,
+    #[serde(default)]
+    units: Units,
+    #[serde(default)]
+    pose: Option<BasePose>,
+    samples: Value,
+}
+
+impl Length {
+    /// Length of the unit in metres.
+    pub fn metres(self) -> f64 {
+        match self {
+            Length::Metre => 1.,
+            Length::Centimetre => 1e-2,
+            Length::Millimetre => 1e-3,
+        }
+    }
+}
+
+impl Units {
+    /// Factors converting distance and curvature to `unit`,
+    /// or to the declared unit of distance if `unit` is None.
+    pub fn factors(&self, unit: Option<Length>) -> (f64, f64) {
+        let unit = match unit.or(self.distance) {
+            Some(unit) => unit,
+            None => return (1., 1.),
+        };
+        let distance = self
+            .distance
+            .map_or(1., |from| from.metres() / unit.metres());
+        let curvature = self
+            .curvature
+            .map_or(1., |from| unit.metres() / from.metres());
+        (distance, curvature)
+    }
+
+    /// Convert samples `(s, ka, kb)` to `unit`.
+    pub fn apply(&self, data: &mut [(f64, f64, f64)], unit: Option<Length>) {
+        let (distance, curvature) = self.factors(unit);
+        for (s, ka, kb) in data.iter_mut() {
+            *s *= distance;
+            *ka *= curvature;
+            *kb *= curvature;
+        }
+    }
+}
+
+impl Frame {
+    /// Parse a frame, as a bare array of samples or an envelope with a supported version.
+    pub fn parse(raw: &[u8]) -> Result<Self, String> {
+        let value = serde_json::from_slice::<Value>(raw)
+            .map_err(|_| "wrong data from source client".to_string())?;
+        if value.is_array() {
+            return Ok(Self {
+                version: 0,
+                header: Header::default(),
+                units: Units::default(),
+                samples: value,
+            });
+        }
+        let version = match value.get("version").map(Value::as_u64) {
+            Some(Some(version)) => version,
+            Some(None) => return Err("version of envelope should be an integer".to_string()),
+            None => return Err("wrong data from source client".to_string()),
+        };
+        if version != VERSION as u64 {
+            return Err(format!("unsupported version of envelope: {}", version));
+        }
+        let envelope = serde_json::from_value::<Envelope>(value)
+            .map_err(|err| format!("wrong envelope from source client: {}", err))?;
+        let pose = match envelope.pose {
+            Some(pose) => Some(
+                Pose::new(pose.position, pose.orientation)
+                    .map_err(|err| format!("reject pose from source client: {}", err))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            version: VERSION,
+            header: Header {
+                seq: envelope.seq,
+                timestamp: envelope.timestamp,
+                pose,
+            },
+            units: envelope.units,
+            samples: envelope.samples,
+        })
+    }
+}
+
+impl BinaryFrame {
+    /// Whether raw data is a binary frame rather than JSON.
+    pub fn is_binary(raw: &[u8]) -> bool {
+        raw.starts_with(&MAGIC)
+    }
+
+    /// Parse a binary frame, whose length should match its count of samples exactly.
+    pub fn parse(raw: &[u8]) -> Result<Self, String> {
+        if !Self::is_binary(raw) {
+            return Err("binary frame should start with magic `CROW`".to_string());
+        }
+        if raw.len() < HEADER_SIZE {
+            return Err(format!(
+                "binary frame is shorter than header: {} bytes",
+                raw.len()
+            ));
+        }
+        let version = raw[4];
+        if version as u32 != VERSION {
+            return Err(format!("unsupported version of binary frame: {}", version));
+        }
+        let flags = raw[5];
+        if flags & !(F64 | SEQ | TIMESTAMP) != 0 {
+            return Err(format!("unknown flags of binary frame: {:#010b}", flags));
+        }
+        let count = u16::from_le_bytes([raw[6], raw[7]]) as usize;
+        let width = if flags & F64 != 0 { 8 } else { 4 };
+        let expected = HEADER_SIZE + count * 3 * width;
+        if raw.len() != expected {
+            return Err(format!(
+                "binary frame of {} samples should be {} bytes, found {}",
+                count,
+                expected,
+                raw.len()
+            ));
+        }
+        let mut seq = [0; 4];
+        seq.copy_from_slice(&raw[8..12]);
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&raw[12..20]);
+        let value = |bytes: &[u8]| {
+            if width == 8 {
+                let mut value = [0; 8];
+                value.copy_from_slice(bytes);
+                f64::from_le_bytes(value)
+            } else {
+                let mut value = [0; 4];
+                value.copy_from_slice(bytes);
+                f32::from_le_bytes(value) as f64
+            }
+        };
+        let samples = raw[HEADER_SIZE..]
+            .chunks_exact(3 * width)
+            .map(|sample| {
+                (
+                    value(&sample[..width]),
+                    value(&sample[width..2 * width]),
+                    value(&sample[2 * width..]),
+                )
+            })
+            .collect();
+        Ok(Self {
+            header: Header {
+                seq: Some(u32::from_le_bytes(seq) as u64).filter(|_| flags & SEQ != 0),
+                timestamp: Some(u64::from_le_bytes(timestamp)).filter(|_| flags & TIMESTAMP != 0),
+                pose: None,
+            },
+            samples,
+        })
+    }
+
+    /// Encode the frame, with f64 samples if `f64` is true.
+    ///
+    /// Sequence numbers are truncated to u32.
+    #[cfg(test)]
+    pub fn encode(&self, f64: bool) -> Vec<u8> {
+        let width = if f64 { 8 } else { 4 };
+        let mut raw = Vec::with_capacity(HEADER_SIZE + self.samples.len() * 3 * width);
+        let mut flags = 0;
+        if f64 {
+            flags |= F64;
+        }
+        if self.header.seq.is_some() {
+            flags |= SEQ;
+        }
+        if self.header.timestamp.is_some() {
+            flags |= TIMESTAMP;
+        }
+        raw.extend_from_slice(&MAGIC);
+        raw.push(VERSION as u8);
+        raw.push(flags);
+        raw.extend_from_slice(&(self.samples.len() as u16).to_le_bytes());
+        raw.extend_from_slice(&(self.header.seq.unwrap_or_default() as u32).to_le_bytes());
+        raw.extend_from_slice(&self.header.timestamp.unwrap_or_default().to_le_bytes());
+        for (s, ka, kb) in self.samples.iter() {
+            for value in [s, ka, kb].iter() {
+                if f64 {
+                    raw.extend_from_slice(&value.to_le_bytes());
+                } else {
+                    raw.extend_from_slice(&(**value as f32).to_le_bytes());
+                }
+            }
+        }
+        raw
+    }
+}
+
+impl Encoding {
+    /// Websocket subprotocol of the encoding.
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            Encoding::Json => "crow.json",
+            Encoding::Binary => "crow.binary",
+        }
+    }
+
+    /// The first encoding among subprotocols offered by a subscriber, e.g. `"chat, crow.binary"`.
+    pub fn negotiate(subprotocols: &str) -> Option<Self> {
+        subprotocols.split(',').map(str::trim).find_map(|protocol| {
+            [Encoding::Json, Encoding::Binary]
+                .iter()
+                .find(|encoding| encoding.subprotocol() == protocol)
+                .cloned()
+        })
+    }
+
+    /// Encode a curve for subscribers of the encoding.
+    pub fn encode<T: Downstream>(self, curve: &T) -> Vec<u8> {
+        match self {
+            Encoding::Json => {
+                let mut encoder = Encoder::new(Vec::new());
+                encoder
+                    .write_all(&serde_json::to_vec(curve).unwrap())
+                    .unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+            Encoding::Binary => curve.to_binary(),
+        }
+    }
+}
+
+impl Downstream for Curve {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn segments(&self) -> Vec<(&[Point], Option<usize>)> {
+        vec![(&self.points, None)]
+    }
+}
+
+impl Downstream for MultiCurve {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn segments(&self) -> Vec<(&[Point], Option<usize>)> {
+        self.segments
+            .iter()
+            .map(|segment| (segment.points.as_slice(), segment.parent))
+            .collect()
+    }
+}
+
+fn distance<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Length>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|unit| unit.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn curvature<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Length>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|unit| match unit.strip_prefix("1/") {
+            Some(length) => length.parse().map_err(serde::de::Error::custom),
+            None => Err(serde::de::Error::custom(format!(
+                "unknown unit of curvature: {}",
+                unit
+            ))),
+        })
+        .transpose()
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "m" => Ok(Length::Metre),
+            "cm" => Ok(Length::Centimetre),
+            "mm" => Ok(Length::Millimetre),
+            _ => Err(format!("unknown unit of length: {}", s)),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Encoding::Json),
+            "binary" => Ok(Encoding::Binary),
+            _ => Err(format!("unknown encoding: {}", s)),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Json => "json",
+            Encoding::Binary => "binary",
+        })
+    }
+}
+
+impl Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Length::Metre => "m",
+            Length::Centimetre => "cm",
+            Length::Millimetre => "mm",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BinaryFrame, Downstream, Encoding, Frame, Header, Length, Units, F64, FRAMES, HEADER_SIZE,
+        MAGIC, NO_PARENT, VERSION,
+    };
+    use crate::curve::{self, Curve, CurveSegment, MultiCurve, Point};
+    use nalgebra::{UnitQuaternion, Vector3};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use serde_json::json;
+
+    fn binary() -> BinaryFrame {
+        BinaryFrame {
+            header: Header {
+                seq: Some(7),
+                timestamp: Some(1590000000000),
+                pose: None,
+            },
+            samples: vec![(0., 0., 0.), (4.5, 0.25, -0.125), (9., f64::NAN, 0.5)],
+        }
+    }
+
+    /// Whether frames are equal, taking NaN as equal to NaN.
+    fn same(a: &BinaryFrame, b: &BinaryFrame) -> bool {
+        let eq = |x: f64, y: f64| x == y || (x.is_nan() && y.is_nan());
+        a.header == b.header
+            && a.samples.len() == b.samples.len()
+            && a.samples
+                .iter()
+                .zip(b.samples.iter())
+                .all(|(p, q)| eq(p.0, q.0) && eq(p.1, q.1) && eq(p.2, q.2))
+    }
+
+    #[test]
+    fn parse() {
+        let legacy = Frame::parse(b"[[0,0,0],[1,0.1,null]]").unwrap();
+        assert_eq!(0, legacy.version);
+        assert_eq!(Header::default(), legacy.header);
+        assert_eq!(json!([[0, 0, 0], [1, 0.1, null]]), legacy.samples);
+
+        let frame = Frame::parse(
+            br#"{"version": 1, "seq": 7, "timestamp": 1590000000000,
+                "units": {"distance": "mm", "curvature": "1/m"},
+                "pose": {"position": [0, 0, 1], "orientation": [2, 0, 0, 0]},
+                "samples": [[0, 0, 0]], "firmware": "2.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(VERSION, frame.version);
+        assert_eq!(Some(7), frame.header.seq);
+        assert_eq!(Some(1590000000000), frame.header.timestamp);
+        let pose = frame.header.pose.unwrap();
+        assert_eq!(1., pose.position.z);
+        assert!(pose.orientation.angle() < 1e-12);
+        assert_eq!(
+            Units {
+                distance: Some(Length::Millimetre),
+                curvature: Some(Length::Metre),
+            },
+            frame.units
+        );
+
+        // fields but samples are optional.
+        let frame = Frame::parse(br#"{"version": 1, "samples": [[0, 0, 0]]}"#).unwrap();
+        assert_eq!(Header::default(), frame.header);
+        assert_eq!(Units::default(), frame.units);
+
+        for raw in [
+            &br#"{"version": 2, "samples": []}"#[..],
+            br#"{"version": "1", "samples": []}"#,
+            br#"{"version": 1}"#,
+            br#"{"samples": []}"#,
+            br#"{"version": 1, "units": {"distance": "ft"}, "samples": []}"#,
+            br#"{"version": 1, "units": {"curvature": "m"}, "samples": []}"#,
+            br#"{"version": 1, "pose": {"position": [0, 0, 0], "orientation": [0, 0, 0, 0]}, "samples": []}"#,
+            b"[[0,0,0]",
+        ]
+        .iter()
+        {
+            assert!(Frame::parse(raw).is_err(), "{}", String::from_utf8_lossy(raw));
+        }
+    }
+
+    #[test]
+    fn units() {
+        let units = Units {
+            distance: Some(Length::Millimetre),
+            curvature: Some(Length::Metre),
+        };
+        // samples in unit of distance if channel has no unit.
+        let mut data = vec![(10., 2., -1.)];
+        units.apply(&mut data, None);
+        assert!((data[0].0 - 10.).abs() < 1e-12);
+        assert!((data[0].1 - 2e-3).abs() < 1e-15 && (data[0].2 + 1e-3).abs() < 1e-15);
+
+        let mut data = vec![(10., 2., f64::NAN)];
+        units.apply(&mut data, Some(Length::Centimetre));
+        assert!((data[0].0 - 1.).abs() < 1e-12);
+        assert!((data[0].1 - 2e-2).abs() < 1e-15 && data[0].2.is_nan());
+
+        assert_eq!((1., 1.), Units::default().factors(Some(Length::Metre)));
+        let curvature = Units {
+            distance: None,
+            curvature: Some(Length::Centimetre),
+        };
+        assert_eq!((1., 1.), curvature.factors(None));
+        assert_eq!("mm".parse(), Ok(Length::Millimetre));
+        assert_eq!("cm", Length::Centimetre.to_string());
+    }
+
+    #[test]
+    fn binary_frame() {
+        let frame = binary();
+        for f64 in [false, true].iter() {
+            let raw = frame.encode(*f64);
+            assert!(BinaryFrame::is_binary(&raw));
+            assert_eq!(HEADER_SIZE + 3 * 3 * if *f64 { 8 } else { 4 }, raw.len());
+            assert!(same(&frame, &BinaryFrame::parse(&raw).unwrap()));
+        }
+
+        // without seq and timestamp.
+        let mut bare = binary();
+        bare.header = Header::default();
+        assert!(same(
+            &bare,
+            &BinaryFrame::parse(&bare.encode(false)).unwrap()
+        ));
+
+        // JSON never starts with magic.
+        assert!(!BinaryFrame::is_binary(b"[[0,0,0]]"));
+        assert!(!BinaryFrame::is_binary(br#"{"version": 1}"#));
+
+        let mut raw = frame.encode(false);
+        raw[0] = b'c';
+        assert_eq!(
+            Err("binary frame should start with magic `CROW`".to_string()),
+            BinaryFrame::parse(&raw)
+        );
+        let mut raw = frame.encode(false);
+        raw[4] = 2;
+        assert!(BinaryFrame::parse(&raw).is_err());
+        let mut raw = frame.encode(false);
+        raw[5] |= 0b1000;
+        assert!(BinaryFrame::parse(&raw).is_err());
+    }
+
+    #[test]
+    fn truncated_and_oversized() {
+        let seed = rand::random();
+        let mut rng = StdRng::seed_from_u64(seed);
+        for f64 in [false, true].iter() {
+            let raw = binary().encode(*f64);
+            for len in 0..raw.len() {
+                assert!(BinaryFrame::parse(&raw[..len]).is_err(), "len {}", len);
+            }
+            for extra in 1..64 {
+                let mut oversized = raw.clone();
+                oversized.extend((0..extra).map(|_| rng.gen::<u8>()));
+                let result = BinaryFrame::parse(&oversized);
+                assert!(result.is_err(), "seed {}, extra {}", seed, extra);
+            }
+            // a count larger than samples.
+            let mut raw = raw.clone();
+            raw[6..8].copy_from_slice(&u16::MAX.to_le_bytes());
+            assert!(BinaryFrame::parse(&raw).is_err(), "seed {}", seed);
+        }
+
+        // random mutations of header and samples never panic,
+        // and an accepted frame matches its length.
+        for _ in 0..10000 {
+            let mut raw = binary().encode(rng.gen());
+            let len = rng.gen_range(0, raw.len() + 32);
+            raw.resize(len, rng.gen());
+            for _ in 0..rng.gen_range(0, 4) {
+                if !raw.is_empty() {
+                    let index = rng.gen_range(0, raw.len().min(HEADER_SIZE + 8));
+                    raw[index] = rng.gen();
+                }
+            }
+            if let Ok(frame) = BinaryFrame::parse(&raw) {
+                let width = if raw[5] & F64 != 0 { 8 } else { 4 };
+                let expected = HEADER_SIZE + frame.samples.len() * 3 * width;
+                assert_eq!(raw.len(), expected, "seed {}", seed);
+                assert_eq!(VERSION, raw[4] as u32, "seed {}", seed);
+            }
+        }
+    }
+
+    fn point(z: f32, angle: f64) -> Point {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle);
+        Point {
+            x: 0.,
+            y: 1.,
+            z,
+            frame: Some(curve::Frame::from_rotation(&rotation)),
+        }
+    }
+
+    fn floats(raw: &[u8]) -> Vec<f32> {
+        raw.chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn downstream() {
+        let mut curve = Curve {
+            timestamp: 1590000000000,
+            seq: Some(3),
+            points: vec![point(1., 0.), point(2., 0.5)],
+            envelope: None,
+            imputed: Vec::new(),
+            closure: None,
+        };
+        let raw = curve.to_binary();
+        assert_eq!(&MAGIC[..], &raw[..4]);
+        assert_eq!(VERSION, raw[4] as u32);
+        assert_eq!(FRAMES, raw[5]);
+        assert_eq!([1, 0], [raw[6], raw[7]]);
+        assert_eq!(1590000000000u64.to_le_bytes(), raw[8..16]);
+        assert_eq!(2u32.to_le_bytes(), raw[16..20]);
+        assert_eq!(NO_PARENT.to_le_bytes(), raw[20..24]);
+        // positions, then quaternions [x, y, z, w].
+        let values = floats(&raw[24..]);
+        assert_eq!(2 * 3 + 2 * 4, values.len());
+        assert_eq!(vec![0., 1., 1., 0., 1., 2.], values[..6].to_vec());
+        assert_eq!(vec![0., 0., 0., 1.], values[6..10].to_vec());
+        assert!((values[11] - 0.25f32.sin()).abs() < 1e-6);
+
+        // without frames.
+        for point in curve.points.iter_mut() {
+            point.frame = None;
+        }
+        let raw = curve.to_binary();
+        assert_eq!(0, raw[5]);
+        assert_eq!(24 + 2 * 12, raw.len());
+
+        assert!(!Encoding::Json.encode(&curve).starts_with(&MAGIC));
+        assert_eq!(raw, Encoding::Binary.encode(&curve));
+
+        let tree = MultiCurve {
+            timestamp: 0,
+            seq: None,
+            segments: vec![
+                CurveSegment {
+                    name: "shaft".to_string(),
+                    parent: None,
+                    points: vec![point(1., 0.)],
+                },
+                CurveSegment {
+                    name: "left".to_string(),
+                    parent: Some(0),
+                    points: vec![point(2., 0.), point(3., 0.)],
+                },
+            ],
+            imputed: Vec::new(),
+        };
+        let raw = tree.to_binary();
+        assert_eq!([2, 0], [raw[6], raw[7]]);
+        assert_eq!(1u32.to_le_bytes(), raw[16..20]);
+        assert_eq!(NO_PARENT.to_le_bytes(), raw[20..24]);
+        assert_eq!(2u32.to_le_bytes(), raw[24..28]);
+        assert_eq!(0u32.to_le_bytes(), raw[28..32]);
+        assert_eq!(3. * 3. + 3. * 4., floats(&raw[32..]).len() as f64);
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(Some(Encoding::Binary), Encoding::negotiate("crow.binary"));
+        assert_eq!(
+            Some(Encoding::Json),
+            Encoding::negotiate("chat, crow.json ,crow.binary")
+        );
+        assert_eq!(None, Encoding::negotiate("chat"));
+        assert_eq!(Ok(Encoding::Binary), "binary".parse());
+        assert!("msgpack".parse::<Encoding>().is_err());
+        assert_eq!("json", Encoding::Json.to_string());
+    }
+}
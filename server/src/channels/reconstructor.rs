@@ -0,0 +1,221 @@
+use super::curvature_splines::{CurvatureSplines, Integrator};
+use super::error::ReconstructError;
+use super::rod::Rod;
+use crate::curve::Point;
+use nalgebra::{Matrix3, Vector3};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An algorithm to reconstruct curve from interpolated curvature.
+pub trait Reconstructor: Send + Sync {
+    /// Reconstruct curve starting at position `ai` with rotation `ri`.
+    fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError>;
+}
+
+/// Reconstructors keyed by name.
+#[derive(Clone)]
+pub struct Registry(HashMap<String, Arc<dyn Reconstructor>>);
+
+/// Legacy algorithm, `CurvatureSplines::curvature_reconstruct`.
+pub struct Curvature;
+
+/// `CurvatureSplines::frenet_reconstruct`.
+pub struct Frenet;
+
+/// `CurvatureSplines::torsion_reconstruct`, samples are (curvature, torsion).
+pub struct Torsion(pub Integrator);
+
+/// `Rod::reconstruct`, fitting an elastic rod to samples.
+pub struct Cosserat(pub Rod);
+
+impl Reconstructor for Curvature {
+    fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        splines.curvature_reconstruct(ai, ri)
+    }
+}
+
+impl Reconstructor for Frenet {
+    fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        splines.frenet_reconstruct(ai, ri)
+    }
+}
+
+impl Reconstructor for Torsion {
+    fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        splines.torsion_reconstruct(ai, ri, self.0)
+    }
+}
+
+impl Reconstructor for Cosserat {
+    fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        self.0.reconstruct(splines, ai, ri)
+    }
+}
+
+impl Registry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Register a reconstructor, replacing the old one with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        reconstructor: impl Reconstructor + 'static,
+    ) {
+        self.0.insert(name.into(), Arc::new(reconstructor));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Reconstructor>> {
+        self.0.get(name).cloned()
+    }
+}
+
+impl Default for Registry {
+    /// Registry of built-in reconstructors.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("curvature", Curvature);
+        registry.register("frenet", Frenet);
+        registry.register("torsion-euler", Torsion(Integrator::Euler));
+        registry.register("torsion-rk4", Torsion(Integrator::RungeKutta4));
+        registry.register("torsion-magnus", Torsion(Integrator::Magnus));
+        registry.register("cosserat", Cosserat(Rod::default()));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frenet, Registry};
+    use crate::channels::curvature_splines::PointSlice;
+    use crate::channels::metrics::{evaluate, helix, positions};
+    use nalgebra::Vector3;
+    use num::{One, Zero};
+
+    const DATA: [(f64, f64, f64); 4] = [
+        (0., 0., 0.),
+        (4.66, 0.21, 0.1),
+        (9.36, 0.27, -0.1),
+        (14.82, 0.086, 0.),
+    ];
+
+    #[test]
+    fn builtin() {
+        let registry = Registry::default();
+        let splines = DATA.interpolate(0.1).unwrap();
+        for name in [
+            "curvature",
+            "frenet",
+            "torsion-euler",
+            "torsion-rk4",
+            "torsion-magnus",
+            "cosserat",
+        ]
+        .iter()
+        {
+            let points = registry
+                .get(name)
+                .unwrap()
+                .reconstruct(&splines, Zero::zero(), One::one())
+                .unwrap();
+            assert_eq!(splines.splines.len(), points.len());
+        }
+        assert!(registry.get("unknown").is_none());
+    }
+
+    #[test]
+    fn register() {
+        let mut registry = Registry::new();
+        assert!(registry.get("default").is_none());
+        registry.register("default", Frenet);
+        assert!(registry.get("default").is_some());
+    }
+
+    #[test]
+    fn accuracy() {
+        // helix starts from tangent z and normal -x.
+        let (kappa, tau, ds) = (0.5f64, 0.2f64, 0.01);
+        let helix = helix(kappa, tau);
+        let stations = (0..=100).map(|i| i as f64 * 0.1).collect::<Vec<_>>();
+        // curvature vector rotates at rate of torsion in the sensor frame.
+        let bending = stations
+            .iter()
+            .map(|s| (*s, kappa * (tau * s).cos(), kappa * (tau * s).sin()))
+            .collect::<Vec<_>>();
+        let frenet_serret = stations
+            .iter()
+            .map(|s| (*s, kappa, tau))
+            .collect::<Vec<_>>();
+
+        let registry = Registry::default();
+        // (name, samples, max error, tip angle error, frechet)
+        for (name, data, max_error, tip_angle_error, frechet) in [
+            ("curvature", &bending, 1e-2, 2e-3, 1e-2),
+            ("frenet", &bending, 1e-2, 2e-3, 1e-2),
+            ("torsion-euler", &frenet_serret, 2e-2, 5e-3, 2e-2),
+            ("torsion-rk4", &frenet_serret, 1e-6, 1e-5, 1e-6),
+            ("torsion-magnus", &frenet_serret, 1e-6, 1e-5, 1e-6),
+            ("cosserat", &bending, 1e-2, 2e-3, 1e-2),
+        ]
+        .iter()
+        {
+            let splines = data.interpolate(ds).unwrap();
+            let mut curve = vec![Vector3::zeros()];
+            curve.extend(positions(
+                &registry
+                    .get(name)
+                    .unwrap()
+                    .reconstruct(&splines, Zero::zero(), One::one())
+                    .unwrap(),
+            ));
+            let reference = (0..curve.len())
+                .map(|i| helix(i as f64 * ds))
+                .collect::<Vec<_>>();
+            let metrics = evaluate(&curve, &reference).unwrap();
+            assert!(metrics.max_error < *max_error, "{}: {:?}", name, metrics);
+            assert!(metrics.tip_error < *max_error, "{}: {:?}", name, metrics);
+            assert!(
+                metrics.rms_error <= metrics.max_error,
+                "{}: {:?}",
+                name,
+                metrics
+            );
+            assert!(
+                metrics.tip_angle_error < *tip_angle_error,
+                "{}: {:?}",
+                name,
+                metrics
+            );
+            assert!(metrics.hausdorff <= metrics.frechet, "{}", name);
+            assert!(metrics.frechet < *frechet, "{}", name);
+            assert!(metrics.length_drift.abs() < 1e-3, "{}", name);
+        }
+    }
+}
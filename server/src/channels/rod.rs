@@ -0,0 +1,402 @@
+use super::curvature_splines::{push_point, validate_rotation, CurvatureSplines};
+use super::error::ReconstructError;
+use crate::curve::Point;
+use nalgebra::{Matrix3, Matrix6, Rotation3, Vector3, Vector6};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Maximum iterations of Levenberg-Marquardt.
+const MAX_ITERATIONS: usize = 50;
+
+/// Weight of the boundary condition at the tip, relative to curvature residuals.
+const BOUNDARY_WEIGHT: f64 = 1e4;
+
+/// Relative step of finite difference.
+const DIFFERENCE_STEP: f64 = 1e-7;
+
+/// Load at the tip of rod, the boundary condition of fitting.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum TipLoad {
+    /// Unknown force and moment.
+    #[default]
+    Wrench,
+
+    /// Unknown force without moment, like a point contact.
+    Force,
+}
+
+/// Kirchhoff rod clamped at the base and loaded only at the tip, inextensible and unshearable.
+///
+/// Without distributed load, internal force is constant and internal moment changes
+/// linearly with position, so curvature of the whole rod follows from the wrench at the base.
+/// The wrench is fitted to measured curvature by regularized least squares,
+/// then the rod is integrated from it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rod {
+    /// bending stiffness of ka and kb; only their ratio matters, as loads are fitted.
+    pub stiffness: (f64, f64),
+
+    /// weight of the squared base wrench, relative to squared curvature residuals.
+    pub regularization: f64,
+
+    pub tip: TipLoad,
+}
+
+/// Internal moment and force at the base in absolute coordinate,
+/// as (moment / stiffness, force * length / stiffness), both in unit of curvature.
+type Wrench = Vector6<f64>;
+
+/// Position and rotation of local frame to absolute frame.
+#[derive(Debug, Copy, Clone)]
+struct State {
+    position: Vector3<f64>,
+    rotation: Rotation3<f64>,
+}
+
+impl Default for Rod {
+    fn default() -> Self {
+        Self {
+            stiffness: (1., 1.),
+            regularization: 1e-6,
+            tip: TipLoad::Wrench,
+        }
+    }
+}
+
+impl Rod {
+    /// Fit the rod to interpolated curvature, then reconstruct it
+    /// starting at position `ai` with rotation `ri`.
+    pub fn reconstruct(
+        &self,
+        splines: &CurvatureSplines,
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Vec<Point>, ReconstructError> {
+        let base = State {
+            position: ai,
+            rotation: validate_rotation(&ri)?.inverse(),
+        };
+        let wrench = self.fit(splines, &base);
+        let mut points = Vec::with_capacity(splines.splines.len());
+        self.integrate(
+            splines.steps(),
+            splines.length(),
+            &base,
+            &wrench,
+            |_, _, state| push_point(&mut points, &state.position, &state.rotation.inverse()),
+        );
+        Ok(points)
+    }
+
+    /// Fit the base wrench by Levenberg-Marquardt, starting from a pure moment
+    /// bending the base as measured.
+    fn fit(&self, splines: &CurvatureSplines, base: &State) -> Wrench {
+        let length = splines.length();
+        let (ka, kb) = splines.splines[0];
+        let (ba, bb) = self.normalized_stiffness();
+        let moment = base.rotation * Vector3::new(bb * kb, -ba * ka, 0.);
+        let mut wrench = Wrench::new(moment.x, moment.y, moment.z, 0., 0., 0.);
+        let mut residuals = self.residuals(splines, base, &wrench);
+        let mut cost = norm_squared(&residuals);
+        let mut damping = 1e-3;
+        for _ in 0..MAX_ITERATIONS {
+            // normal equations of the jacobian by forward difference.
+            let mut jacobian = Vec::with_capacity(6);
+            for i in 0..6 {
+                let h = DIFFERENCE_STEP * (wrench[i].abs() + 1. / length);
+                let mut perturbed = wrench;
+                perturbed[i] += h;
+                let column = self
+                    .residuals(splines, base, &perturbed)
+                    .iter()
+                    .zip(&residuals)
+                    .map(|(r, r0)| (r - r0) / h)
+                    .collect::<Vec<_>>();
+                jacobian.push(column);
+            }
+            let jtj = Matrix6::from_fn(|i, j| dot(&jacobian[i], &jacobian[j]));
+            let gradient = Vector6::from_fn(|i, _| dot(&jacobian[i], &residuals));
+
+            let mut improved = false;
+            while damping < 1e12 {
+                let system = jtj + Matrix6::from_diagonal(&(jtj.diagonal() * damping));
+                let step = match system.cholesky() {
+                    Some(cholesky) => -cholesky.solve(&gradient),
+                    None => {
+                        damping *= 10.;
+                        continue;
+                    }
+                };
+                let candidate = wrench + step;
+                let candidate_residuals = self.residuals(splines, base, &candidate);
+                let candidate_cost = norm_squared(&candidate_residuals);
+                if candidate_cost < cost {
+                    let converged = cost - candidate_cost <= 1e-10 * cost
+                        || step.norm() <= 1e-10 * (wrench.norm() + 1. / length);
+                    wrench = candidate;
+                    residuals = candidate_residuals;
+                    cost = candidate_cost;
+                    damping = (damping / 10.).max(1e-12);
+                    improved = !converged;
+                    break;
+                }
+                damping *= 10.;
+            }
+            if !improved {
+                break;
+            }
+        }
+        wrench
+    }
+
+    /// Curvature residuals of each step weighted by its length, the regularization
+    /// and the boundary condition.
+    fn residuals(&self, splines: &CurvatureSplines, base: &State, wrench: &Wrench) -> Vec<f64> {
+        let length = splines.length();
+        let mut residuals = Vec::with_capacity(splines.splines.len() * 2 + 9);
+        let mut tip = *base;
+        self.integrate(
+            splines.steps(),
+            length,
+            base,
+            wrench,
+            |index, (ka, kb), state| {
+                let (measured_a, measured_b) = splines.splines[index];
+                let weight = splines.steps()[index].sqrt();
+                residuals.push((ka - measured_a) * weight);
+                residuals.push((kb - measured_b) * weight);
+                tip = *state;
+            },
+        );
+        let weight = (self.regularization * length).sqrt();
+        residuals.extend(wrench.iter().map(|v| v * weight));
+        if self.tip == TipLoad::Force {
+            let weight = (BOUNDARY_WEIGHT * length).sqrt();
+            residuals.extend(
+                moment(base, length, wrench, &tip)
+                    .iter()
+                    .map(|v| v * weight),
+            );
+        }
+        residuals
+    }
+
+    /// Integrate the rod from the base by the midpoint rule,
+    /// visiting each step with its index, (ka, kb) at its start and the state at its end.
+    fn integrate(
+        &self,
+        steps: &[f64],
+        length: f64,
+        base: &State,
+        wrench: &Wrench,
+        mut visit: impl FnMut(usize, (f64, f64), &State),
+    ) {
+        let mut state = *base;
+        for (index, h) in steps.iter().cloned().enumerate() {
+            let start = self.curvature(base, length, wrench, &state);
+            let middle = advance(&state, start, h / 2.);
+            state = advance(&state, self.curvature(base, length, wrench, &middle), h);
+            state.rotation.renormalize();
+            visit(index, start, &state);
+        }
+    }
+
+    /// (ka, kb) of a state under the base wrench.
+    fn curvature(&self, base: &State, length: f64, wrench: &Wrench, state: &State) -> (f64, f64) {
+        let (ba, bb) = self.normalized_stiffness();
+        let local = state.rotation.inverse() * moment(base, length, wrench, state);
+        // the curve bends toward -(ka, kb), local angular velocity is (kb, -ka, 0).
+        (-local.y / ba, local.x / bb)
+    }
+
+    /// Stiffness relative to their mean.
+    fn normalized_stiffness(&self) -> (f64, f64) {
+        let (a, b) = self.stiffness;
+        let mean = (a + b) / 2.;
+        (a / mean, b / mean)
+    }
+}
+
+/// Internal moment at a state, in absolute coordinate and unit of curvature.
+fn moment(base: &State, length: f64, wrench: &Wrench, state: &State) -> Vector3<f64> {
+    let moment = Vector3::new(wrench[0], wrench[1], wrench[2]);
+    let force = Vector3::new(wrench[3], wrench[4], wrench[5]) / length;
+    moment - (state.position - base.position).cross(&force)
+}
+
+/// Move along an arc of constant (ka, kb) by h.
+fn advance(state: &State, (ka, kb): (f64, f64), h: f64) -> State {
+    let omega = Vector3::new(kb, -ka, 0.);
+    let k2 = omega.norm_squared();
+    let theta = k2.sqrt() * h;
+    // displacement along tangent, and toward the bending direction scaled by curvature.
+    let (along, across) = if theta < 1e-6 {
+        (h, h * h / 2.)
+    } else {
+        (theta.sin() / k2.sqrt(), (1. - theta.cos()) / k2)
+    };
+    let displacement = Vector3::z() * along + omega.cross(&Vector3::z()) * across;
+    State {
+        position: state.position + state.rotation * displacement,
+        rotation: state.rotation * Rotation3::new(omega * h),
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn norm_squared(v: &[f64]) -> f64 {
+    dot(v, v)
+}
+
+impl FromStr for TipLoad {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrench" => Ok(TipLoad::Wrench),
+            "force" => Ok(TipLoad::Force),
+            _ => Err(format!("unknown tip load: {}", s)),
+        }
+    }
+}
+
+impl Display for TipLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TipLoad::Wrench => "wrench",
+            TipLoad::Force => "force",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rod, State, TipLoad, Wrench};
+    use crate::channels::curvature_splines::PointSlice;
+    use crate::channels::metrics::{evaluate, positions, Metrics};
+    use crate::curve::Point;
+    use nalgebra::{Rotation3, Vector3};
+    use num::{One, Zero};
+
+    const LENGTH: f64 = 10.;
+
+    /// Step of reference rods.
+    const DS: f64 = 0.01;
+
+    /// (s, ka, kb) of stations.
+    type Samples = Vec<(f64, f64, f64)>;
+
+    /// Positions and (s, ka, kb) of a rod under wrench, integrated by fine steps,
+    /// from the base to the last sample.
+    fn rod(rod: &Rod, wrench: &Wrench) -> (Vec<Vector3<f64>>, Samples) {
+        let steps = vec![DS; (LENGTH / DS).round() as usize];
+        let base = State {
+            position: Vector3::zeros(),
+            rotation: Rotation3::identity(),
+        };
+        let mut curve = vec![Vector3::zeros()];
+        let mut samples = Vec::new();
+        rod.integrate(&steps, LENGTH, &base, wrench, |index, (ka, kb), state| {
+            curve.push(state.position);
+            samples.push((index as f64 * DS, ka, kb));
+        });
+        curve.pop();
+        (curve, samples)
+    }
+
+    /// Metrics of frenet and rod reconstructions from sensors every `spacing`.
+    fn compare(model: &Rod, wrench: &Wrench, spacing: f64) -> (Metrics, Metrics) {
+        let (reference, samples) = rod(model, wrench);
+        let sensors = samples
+            .iter()
+            .step_by((spacing / DS).round() as usize)
+            .chain(samples.last())
+            .cloned()
+            .collect::<Vec<_>>();
+        let splines = sensors.interpolate(0.05).unwrap();
+        let metrics = |points: &[Point]| {
+            let mut curve = vec![Vector3::zeros()];
+            curve.extend(positions(points));
+            evaluate(&curve, &reference).unwrap()
+        };
+        (
+            metrics(
+                &splines
+                    .frenet_reconstruct(Zero::zero(), One::one())
+                    .unwrap(),
+            ),
+            metrics(
+                &model
+                    .reconstruct(&splines, Zero::zero(), One::one())
+                    .unwrap(),
+            ),
+        )
+    }
+
+    #[test]
+    fn cantilever() {
+        // a tip force bending the rod toward -x and y, with a moment twisting it out of plane.
+        let wrench = Wrench::new(0.05, 0.1, 0.02, -0.1, 0.05, 0.);
+        for stiffness in [(1., 1.), (1., 2.)].iter() {
+            let model = Rod {
+                stiffness: *stiffness,
+                ..Rod::default()
+            };
+            // the fit follows interpolated curvature, so it gains less from sparser sensors.
+            for (spacing, gain) in [(1., 2.), (0.5, 4.)].iter() {
+                let (frenet, fitted) = compare(&model, &wrench, *spacing);
+                let message = format!("{:?} {}:\n{:?}\n{:?}", stiffness, spacing, frenet, fitted);
+                assert!(fitted.rms_error < frenet.rms_error / gain, "{}", message);
+                assert!(fitted.tip_error < frenet.tip_error / gain, "{}", message);
+                assert!(
+                    fitted.tip_angle_error < frenet.tip_angle_error,
+                    "{}",
+                    message
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tip_force() {
+        // a moment-free tip, so the moment at the base is that of the force around the tip.
+        let model = Rod {
+            tip: TipLoad::Force,
+            ..Rod::default()
+        };
+        let force = Vector3::new(-0.2, 0.1, -0.1);
+        let mut wrench = Wrench::new(0., 0., 0., force.x, force.y, force.z);
+        for _ in 0..50 {
+            let steps = vec![DS; (LENGTH / DS).round() as usize];
+            let mut tip = Vector3::zeros();
+            let base = State {
+                position: Vector3::zeros(),
+                rotation: Rotation3::identity(),
+            };
+            model.integrate(&steps, LENGTH, &base, &wrench, |_, _, state| {
+                tip = state.position
+            });
+            let moment = tip.cross(&force) / LENGTH;
+            wrench = Wrench::new(moment.x, moment.y, moment.z, force.x, force.y, force.z);
+        }
+        let (frenet, fitted) = compare(&model, &wrench, 2.);
+        assert!(
+            fitted.rms_error < frenet.rms_error / 4.,
+            "{:?}\n{:?}",
+            frenet,
+            fitted
+        );
+        assert!(
+            fitted.tip_error < frenet.tip_error / 4.,
+            "{:?}\n{:?}",
+            frenet,
+            fitted
+        );
+
+        assert_eq!(Ok(TipLoad::Force), "force".parse());
+        assert!("torque".parse::<TipLoad>().is_err());
+        assert_eq!("wrench", TipLoad::Wrench.to_string());
+    }
+}
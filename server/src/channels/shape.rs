@@ -0,0 +1,330 @@
+use super::curvature_splines::CurvatureSplines;
+use super::error::ReconstructError;
+use crate::curve::Point;
+use nalgebra::{Matrix3, Quaternion, Rotation3, UnitQuaternion, Vector3};
+
+/// Position and orientation of local frame, in absolute coordinate.
+///
+/// Orientation rotates local frame to absolute frame, whose z axis is the tangent.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pose {
+    pub position: Vector3<f64>,
+    pub orientation: UnitQuaternion<f64>,
+}
+
+/// The point of a curve closest to a target.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Closest {
+    /// arc length from the base.
+    pub s: f64,
+    pub pose: Pose,
+    pub distance: f64,
+}
+
+/// A reconstructed curve from its base, answering queries between samples.
+///
+/// Positions are interpolated by cubic Hermite with tangents of frames,
+/// and orientations by spherical linear interpolation.
+#[derive(Debug, Clone)]
+pub struct ReconstructedCurve {
+    // arc length of each sample from the base, starting at 0.
+    stations: Vec<f64>,
+    poses: Vec<Pose>,
+}
+
+impl Pose {
+    /// Pose from position and orientation as quaternion [w, x, y, z], which is normalized.
+    pub fn new(position: [f64; 3], orientation: [f64; 4]) -> Result<Self, ReconstructError> {
+        let [x, y, z] = position;
+        let [w, i, j, k] = orientation;
+        let quaternion = Quaternion::new(w, i, j, k);
+        if !(position.iter().all(|v| v.is_finite()) && quaternion.norm().is_finite()) {
+            return Err(ReconstructError::NonFinitePose);
+        }
+        if quaternion.norm() < 1e-9 {
+            return Err(ReconstructError::SingularRotation);
+        }
+        Ok(Self {
+            position: Vector3::new(x, y, z),
+            orientation: UnitQuaternion::from_quaternion(quaternion),
+        })
+    }
+
+    /// Pose of reconstructor input, `ri` maps absolute coordinate to relative coordinate.
+    pub fn from_base(ai: Vector3<f64>, ri: Matrix3<f64>) -> Result<Self, ReconstructError> {
+        if !(ri.iter().all(|v| v.is_finite()) && ri.determinant() > 1e-9) {
+            return Err(ReconstructError::SingularRotation);
+        }
+        let mut rotation = Rotation3::from_matrix_unchecked(ri.transpose());
+        rotation.renormalize();
+        Ok(Self {
+            position: ai,
+            orientation: UnitQuaternion::from_rotation_matrix(&rotation),
+        })
+    }
+
+    /// Position `ai` and rotation `ri` of reconstructor input.
+    pub fn to_base(self) -> (Vector3<f64>, Matrix3<f64>) {
+        (
+            self.position,
+            *self.orientation.inverse().to_rotation_matrix().matrix(),
+        )
+    }
+
+    /// Unit tangent.
+    pub fn tangent(&self) -> Vector3<f64> {
+        self.orientation * Vector3::z()
+    }
+}
+
+impl ReconstructedCurve {
+    /// Wrap points reconstructed from splines, starting at position `ai` with rotation `ri`.
+    ///
+    /// A point without frame takes the frame of its last point, turned to its chord.
+    pub fn new(
+        splines: &CurvatureSplines,
+        points: &[Point],
+        ai: Vector3<f64>,
+        ri: Matrix3<f64>,
+    ) -> Result<Self, ReconstructError> {
+        let steps = splines.steps();
+        if points.len() != steps.len() {
+            return Err(ReconstructError::PointMismatch {
+                steps: steps.len(),
+                found: points.len(),
+            });
+        }
+        let base = Pose::from_base(ai, ri)?;
+        let mut stations = Vec::with_capacity(points.len() + 1);
+        let mut poses = Vec::with_capacity(points.len() + 1);
+        stations.push(0.);
+        poses.push(base);
+        for (index, (point, ds)) in points.iter().zip(steps.iter()).enumerate() {
+            let position = Vector3::new(point.x as f64, point.y as f64, point.z as f64);
+            if !position.iter().all(|v| v.is_finite()) {
+                return Err(ReconstructError::NonFinite { index });
+            }
+            let last = poses[poses.len() - 1];
+            let orientation = match point.frame {
+                Some(frame) => {
+                    let q = frame.rotation().coords;
+                    UnitQuaternion::from_quaternion(Quaternion::new(
+                        q[3] as f64,
+                        q[0] as f64,
+                        q[1] as f64,
+                        q[2] as f64,
+                    ))
+                }
+                None => {
+                    UnitQuaternion::rotation_between(&last.tangent(), &(position - last.position))
+                        .unwrap_or_else(UnitQuaternion::identity)
+                        * last.orientation
+                }
+            };
+            stations.push(stations[stations.len() - 1] + ds);
+            poses.push(Pose {
+                position,
+                orientation,
+            });
+        }
+        Ok(Self { stations, poses })
+    }
+
+    /// Arc length from the base to the tip.
+    pub fn length(&self) -> f64 {
+        self.stations[self.stations.len() - 1]
+    }
+
+    /// Pose of the base.
+    pub fn base(&self) -> Pose {
+        self.poses[0]
+    }
+
+    /// Pose of the tip.
+    pub fn tip(&self) -> Pose {
+        self.poses[self.poses.len() - 1]
+    }
+
+    /// Samples of the curve, as arc length and pose.
+    pub fn samples(&self) -> impl Iterator<Item = (f64, &Pose)> {
+        self.stations.iter().cloned().zip(self.poses.iter())
+    }
+
+    /// Pose at arc length s from the base, None if s is out of the curve.
+    pub fn pose_at(&self, s: f64) -> Option<Pose> {
+        if !(s >= 0. && s <= self.length()) {
+            return None;
+        }
+        let index = self
+            .stations
+            .partition_point(|station| *station < s)
+            .max(1)
+            .min(self.stations.len() - 1);
+        let (s0, s1) = (self.stations[index - 1], self.stations[index]);
+        Some(self.interpolate(index, (s - s0) / (s1 - s0)))
+    }
+
+    /// The point closest to target, searched on chords between samples.
+    pub fn closest_point(&self, target: &Vector3<f64>) -> Closest {
+        let mut closest = Closest {
+            s: 0.,
+            pose: self.base(),
+            distance: (self.base().position - target).norm(),
+        };
+        for index in 1..self.poses.len() {
+            let (p0, p1) = (self.poses[index - 1].position, self.poses[index].position);
+            let chord = p1 - p0;
+            let t = if chord.norm_squared() > 0. {
+                ((target - p0).dot(&chord) / chord.norm_squared()).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let pose = self.interpolate(index, t);
+            let distance = (pose.position - target).norm();
+            if distance < closest.distance {
+                let (s0, s1) = (self.stations[index - 1], self.stations[index]);
+                closest = Closest {
+                    s: s0 + (s1 - s0) * t,
+                    pose,
+                    distance,
+                };
+            }
+        }
+        closest
+    }
+
+    /// Axis-aligned bounding box of samples, as (min, max) corners.
+    pub fn bounding_box(&self) -> (Vector3<f64>, Vector3<f64>) {
+        self.poses.iter().fold(
+            (self.poses[0].position, self.poses[0].position),
+            |(min, max), pose| {
+                (
+                    min.zip_map(&pose.position, f64::min),
+                    max.zip_map(&pose.position, f64::max),
+                )
+            },
+        )
+    }
+
+    /// Pose at t in [0, 1] between sample index - 1 and sample index.
+    fn interpolate(&self, index: usize, t: f64) -> Pose {
+        let (a, b) = (&self.poses[index - 1], &self.poses[index]);
+        let h = self.stations[index] - self.stations[index - 1];
+        // cubic hermite basis
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let position = a.position * (2. * t3 - 3. * t2 + 1.)
+            + a.tangent() * (h * (t3 - 2. * t2 + t))
+            + b.position * (-2. * t3 + 3. * t2)
+            + b.tangent() * (h * (t3 - t2));
+        Pose {
+            position,
+            orientation: a.orientation.slerp(&b.orientation, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReconstructedCurve;
+    use crate::channels::curvature_splines::PointSlice;
+    use crate::channels::error::ReconstructError;
+    use nalgebra::{Matrix3, Vector3};
+    use num::{One, Zero};
+
+    /// Arc of curvature k from the origin toward z, bending toward -x.
+    fn arc(k: f64, s: f64) -> Vector3<f64> {
+        Vector3::new(-(1. - (k * s).cos()) / k, 0., (k * s).sin() / k)
+    }
+
+    fn curve(k: f64, length: f64, ds: f64) -> ReconstructedCurve {
+        let splines = [(0., k, 0.), (length, k, 0.)].interpolate(ds).unwrap();
+        let points = splines
+            .frenet_reconstruct(Zero::zero(), One::one())
+            .unwrap();
+        ReconstructedCurve::new(&splines, &points, Zero::zero(), One::one()).unwrap()
+    }
+
+    #[test]
+    fn pose() {
+        let k = 0.5;
+        let curve = curve(k, 3., 0.1);
+        assert!((curve.length() - 3.).abs() < 1e-12);
+        for i in 0..=300 {
+            let s = i as f64 * 0.01;
+            let pose = curve.pose_at(s).unwrap();
+            assert!((pose.position - arc(k, s)).norm() < 1e-5, "{}", s);
+            // tangent turns by k * s around y axis.
+            let tangent = Vector3::new(-(k * s).sin(), 0., (k * s).cos());
+            assert!((pose.tangent() - tangent).norm() < 1e-5, "{}", s);
+        }
+        assert!((curve.tip().position - arc(k, 3.)).norm() < 1e-5);
+        assert_eq!(curve.base().position, Vector3::zeros());
+        assert!(curve.pose_at(-0.1).is_none());
+        assert!(curve.pose_at(3.1).is_none());
+        assert!(curve.pose_at(f64::NAN).is_none());
+    }
+
+    #[test]
+    fn closest_point() {
+        let k = 0.5;
+        let curve = curve(k, 3., 0.1);
+        // points off the arc along its normal, toward and away from the center.
+        for (s, offset) in [(0.75, 0.2), (1.55, -0.3), (2.95, 0.1)].iter() {
+            let normal = Vector3::new(-(k * s).cos(), 0., -(k * s).sin());
+            let target = arc(k, *s) + normal * *offset;
+            let closest = curve.closest_point(&target);
+            assert!((closest.s - s).abs() < 1e-3, "{:?}", closest);
+            assert!(
+                (closest.distance - offset.abs()).abs() < 1e-4,
+                "{:?}",
+                closest
+            );
+        }
+        // beyond the tip.
+        let target = curve.tip().position + curve.tip().tangent();
+        let closest = curve.closest_point(&target);
+        assert!((closest.s - 3.).abs() < 1e-12 && (closest.distance - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let k = 0.5;
+        let (min, max) = curve(k, 3., 0.1).bounding_box();
+        let tip = arc(k, 3.);
+        assert!(
+            (min - Vector3::new(tip.x, 0., 0.)).norm() < 1e-5,
+            "{:?}",
+            min
+        );
+        // the arc turns less than a quarter.
+        assert!(
+            (max - Vector3::new(0., 0., tip.z)).norm() < 1e-5,
+            "{:?}",
+            max
+        );
+    }
+
+    #[test]
+    fn without_frames() {
+        let splines = [(0., 0.5, 0.), (3., 0.5, 0.)].interpolate(0.01).unwrap();
+        let ri = Matrix3::new(0., 0., 1., 0., 1., 0., -1., 0., 0.);
+        let mut points = splines.frenet_reconstruct(Zero::zero(), ri).unwrap();
+        let curve = ReconstructedCurve::new(&splines, &points, Zero::zero(), ri).unwrap();
+        for point in points.iter_mut() {
+            point.frame = None;
+        }
+        let chords = ReconstructedCurve::new(&splines, &points, Zero::zero(), ri).unwrap();
+        for ((_, p), (_, q)) in curve.samples().zip(chords.samples()) {
+            assert!(p.orientation.angle_to(&q.orientation) < 1e-2);
+        }
+
+        assert_eq!(
+            Some(ReconstructError::PointMismatch {
+                steps: splines.steps().len(),
+                found: 1
+            }),
+            ReconstructedCurve::new(&splines, &points[..1], Zero::zero(), ri).err()
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use super::device::Device;
+use super::error::ReconstructError;
+use super::shape::Pose;
+use crate::curve::{CurveSegment, Point};
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Rigid transform of a segment base relative to the tip frame of its parent.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    /// translation in the tip frame of parent.
+    pub position: [f64; 3],
+
+    /// rotation as quaternion [w, x, y, z], in the tip frame of parent.
+    pub orientation: [f64; 4],
+}
+
+/// A curve segment with its own sensor set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub name: String,
+
+    /// name of the parent segment; a root segment starts at the base of channel.
+    #[serde(default)]
+    pub parent: Option<String>,
+
+    #[serde(default)]
+    pub transform: Transform,
+
+    #[serde(default)]
+    pub device: Device,
+}
+
+/// A tree of segments, each one starts at the tip of its parent, e.g.
+/// `{"segments": [{"name": "proximal"}, {"name": "distal", "parent": "proximal"}]}`.
+///
+/// Segments are listed with parents before their children,
+/// so frames of sensors are sent in the same order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Segment>", into = "Vec<Segment>")]
+pub struct Structure {
+    segments: Vec<Segment>,
+
+    // index of parent of each segment.
+    parents: Vec<Option<usize>>,
+}
+
+/// Description of a structure file.
+#[derive(Deserialize)]
+struct Description {
+    segments: Structure,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: [0.; 3],
+            orientation: [1., 0., 0., 0.],
+        }
+    }
+}
+
+impl Transform {
+    /// Pose of a child base from the tip pose of its parent.
+    pub fn apply(&self, tip: &Pose) -> Pose {
+        let [x, y, z] = self.position;
+        let [w, i, j, k] = self.orientation;
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k));
+        Pose {
+            position: tip.position + tip.orientation * Vector3::new(x, y, z),
+            orientation: tip.orientation * rotation,
+        }
+    }
+}
+
+impl From<Structure> for Vec<Segment> {
+    fn from(structure: Structure) -> Self {
+        structure.segments
+    }
+}
+
+impl std::convert::TryFrom<Vec<Segment>> for Structure {
+    type Error = String;
+
+    fn try_from(segments: Vec<Segment>) -> Result<Self, Self::Error> {
+        if segments.is_empty() {
+            return Err("structure has no segment".to_string());
+        }
+        let mut indices = HashMap::new();
+        let mut parents = Vec::with_capacity(segments.len());
+        for (index, segment) in segments.iter().enumerate() {
+            let parent = match &segment.parent {
+                None => None,
+                Some(name) => match indices.get(name.as_str()) {
+                    Some(parent) => Some(*parent),
+                    None => return Err(format!("parent of {} is not listed before", segment.name)),
+                },
+            };
+            let Transform {
+                position,
+                orientation,
+            } = segment.transform;
+            let norm = orientation.iter().map(|v| v * v).sum::<f64>().sqrt();
+            if !(position.iter().all(|v| v.is_finite()) && norm.is_finite() && norm > 1e-9) {
+                return Err(format!("invalid transform of {}", segment.name));
+            }
+            if indices.insert(segment.name.as_str(), index).is_some() {
+                return Err(format!("duplicated segment {}", segment.name));
+            }
+            parents.push(parent);
+        }
+        Ok(Self { segments, parents })
+    }
+}
+
+impl Structure {
+    /// Load structure from a json file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let description: Description = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(description.segments)
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Reconstruct segments from the base of channel, in order of the structure.
+    ///
+    /// `reconstruct` maps a segment, its data and its base pose to its points and tip pose.
+    pub fn reconstruct(
+        &self,
+        base: &Pose,
+        data: &[Vec<(f64, f64, f64)>],
+        reconstruct: impl Fn(
+            &Segment,
+            &[(f64, f64, f64)],
+            &Pose,
+        ) -> Result<(Vec<Point>, Pose), ReconstructError>,
+    ) -> Result<Vec<CurveSegment>, ReconstructError> {
+        if data.len() != self.segments.len() {
+            return Err(ReconstructError::SegmentMismatch {
+                segments: self.segments.len(),
+                found: data.len(),
+            });
+        }
+        let mut tips: Vec<Pose> = Vec::with_capacity(self.segments.len());
+        let mut curves = Vec::with_capacity(self.segments.len());
+        for ((segment, parent), data) in self.segments.iter().zip(&self.parents).zip(data) {
+            let start = segment
+                .transform
+                .apply(parent.map(|index| &tips[index]).unwrap_or(base));
+            let (points, tip) = reconstruct(segment, data, &start)?;
+            tips.push(tip);
+            curves.push(CurveSegment {
+                name: segment.name.clone(),
+                parent: *parent,
+                points,
+            });
+        }
+        Ok(curves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Description, Structure};
+    use crate::channels::error::ReconstructError;
+    use crate::channels::shape::Pose;
+    use crate::channels::ChannelConfig;
+    use nalgebra::Vector3;
+    use num::{One, Zero};
+
+    fn parse(json: &str) -> Result<Structure, serde_json::Error> {
+        serde_json::from_str::<Description>(json).map(|description| description.segments)
+    }
+
+    #[test]
+    fn load() {
+        let structure = parse(
+            r#"{"segments": [
+                {"name": "proximal", "device": {"length": 5}},
+                {"name": "left", "parent": "proximal", "transform": {"position": [0, 0, 1]}},
+                {"name": "right", "parent": "proximal"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(3, structure.segments().len());
+        assert_eq!(vec![None, Some(0), Some(0)], structure.parents);
+        assert_eq!(Some(5.), structure.segments()[0].device.length);
+
+        for json in [
+            r#"{"segments": []}"#,
+            r#"{"segments": [{"name": "a", "parent": "b"}, {"name": "b"}]}"#,
+            r#"{"segments": [{"name": "a"}, {"name": "a"}]}"#,
+            r#"{"segments": [{"name": "a", "transform": {"orientation": [0, 0, 0, 0]}}]}"#,
+        ]
+        .iter()
+        {
+            assert!(parse(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn reconstruct() {
+        // a straight segment, a rigid link turning 90 degrees around y, then two branches.
+        let structure = parse(
+            r#"{"segments": [
+                {"name": "shaft"},
+                {"name": "left", "parent": "shaft",
+                 "transform": {"position": [0, 0, 1], "orientation": [0.7071067811865476, 0, 0.7071067811865476, 0]}},
+                {"name": "right", "parent": "shaft",
+                 "transform": {"orientation": [0.7071067811865476, 0, -0.7071067811865476, 0]}}
+            ]}"#,
+        )
+        .unwrap();
+        let config = ChannelConfig::default();
+        let straight = vec![(0., 0., 0.), (2., 0., 0.)];
+        let base = Pose::from_base(Zero::zero(), One::one()).unwrap();
+        let curves = structure
+            .reconstruct(
+                &base,
+                &[straight.clone(), straight.clone(), straight.clone()],
+                |segment, data, base| config.reconstruct_segment(data, &segment.device, base),
+            )
+            .unwrap();
+        let tip = |index: usize| {
+            let point = curves[index].points.last().unwrap();
+            Vector3::new(point.x as f64, point.y as f64, point.z as f64)
+        };
+        assert!((tip(0) - Vector3::new(0., 0., 2.)).norm() < 1e-5);
+        assert!(
+            (tip(1) - Vector3::new(2., 0., 3.)).norm() < 1e-5,
+            "{}",
+            tip(1)
+        );
+        assert!(
+            (tip(2) - Vector3::new(-2., 0., 2.)).norm() < 1e-5,
+            "{}",
+            tip(2)
+        );
+        assert_eq!(Some(0), curves[2].parent);
+
+        assert_eq!(
+            Some(ReconstructError::SegmentMismatch {
+                segments: 3,
+                found: 1
+            }),
+            structure
+                .reconstruct(&base, &[straight], |segment, data, base| {
+                    config.reconstruct_segment(data, &segment.device, base)
+                })
+                .err()
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use super::shape::Pose;
+use std::collections::VecDeque;
+
+/// Number of poses kept to align with curvature frames.
+const CAPACITY: usize = 64;
+
+/// Timestamped base poses from an external tracker.
+///
+/// A single pose is a standing pose, held for all frames until the next one.
+#[derive(Debug, Clone, Default)]
+pub struct Tracker {
+    // poses in order of timestamp, in milliseconds.
+    poses: VecDeque<(u64, Pose)>,
+}
+
+impl Tracker {
+    /// Record a pose at timestamp, dropping the oldest one if full.
+    pub fn push(&mut self, timestamp: u64, pose: Pose) {
+        let index = self.poses.partition_point(|(time, _)| *time <= timestamp);
+        if index > 0 && self.poses[index - 1].0 == timestamp {
+            self.poses[index - 1].1 = pose;
+            return;
+        }
+        self.poses.insert(index, (timestamp, pose));
+        if self.poses.len() > CAPACITY {
+            self.poses.pop_front();
+        }
+    }
+
+    /// Pose at timestamp, interpolated between the two nearest poses,
+    /// or the nearest one out of their range. None if no pose is tracked.
+    pub fn at(&self, timestamp: u64) -> Option<Pose> {
+        let index = self.poses.partition_point(|(time, _)| *time <= timestamp);
+        if index == 0 {
+            return self.poses.front().map(|(_, pose)| *pose);
+        }
+        if index == self.poses.len() {
+            return self.poses.back().map(|(_, pose)| *pose);
+        }
+        let (t0, a) = &self.poses[index - 1];
+        let (t1, b) = &self.poses[index];
+        let t = (timestamp - t0) as f64 / (t1 - t0) as f64;
+        Some(Pose {
+            position: a.position.lerp(&b.position, t),
+            orientation: a.orientation.slerp(&b.orientation, t),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tracker, CAPACITY};
+    use crate::channels::error::ReconstructError;
+    use crate::channels::shape::Pose;
+    use nalgebra::{UnitQuaternion, Vector3};
+    use std::f64::consts::FRAC_PI_2;
+
+    fn pose(x: f64, angle: f64) -> Pose {
+        Pose {
+            position: Vector3::new(x, 0., 0.),
+            orientation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle),
+        }
+    }
+
+    #[test]
+    fn align() {
+        let mut tracker = Tracker::default();
+        assert!(tracker.at(0).is_none());
+
+        // a standing pose.
+        tracker.push(100, pose(1., 0.));
+        assert_eq!(Some(pose(1., 0.)), tracker.at(0));
+        assert_eq!(Some(pose(1., 0.)), tracker.at(1000));
+
+        // poses out of order are sorted by timestamp.
+        tracker.push(300, pose(3., FRAC_PI_2));
+        tracker.push(200, pose(2., 0.));
+        let middle = tracker.at(250).unwrap();
+        assert!((middle.position.x - 2.5).abs() < 1e-12);
+        assert!((middle.orientation.angle() - FRAC_PI_2 / 2.).abs() < 1e-12);
+        assert_eq!(Some(pose(3., FRAC_PI_2)), tracker.at(400));
+
+        // a pose at the same timestamp replaces the old one.
+        tracker.push(300, pose(4., 0.));
+        assert_eq!(Some(pose(4., 0.)), tracker.at(300));
+
+        for time in 0..CAPACITY as u64 {
+            tracker.push(1000 + time, pose(time as f64, 0.));
+        }
+        assert_eq!(Some(pose(0., 0.)), tracker.at(0));
+
+        let normalized = Pose::new([1., 0., 0.], [2., 0., 2., 0.]).unwrap();
+        assert!(
+            normalized
+                .orientation
+                .angle_to(&pose(1., FRAC_PI_2).orientation)
+                < 1e-12
+        );
+        assert_eq!(
+            Some(ReconstructError::SingularRotation),
+            Pose::new([0.; 3], [0.; 4]).err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonFinitePose),
+            Pose::new([f64::NAN, 0., 0.], [1., 0., 0., 0.]).err()
+        );
+        assert_eq!(
+            Some(ReconstructError::NonFinitePose),
+            Pose::new([0.; 3], [f64::INFINITY, 0., 0., 0.]).err()
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use super::error::ReconstructError;
+use crate::curve::{Envelope, Point};
+use nalgebra::{Matrix3, Vector3};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Gaussian noise of sensors, to estimate uncertainty of shape by Monte-Carlo, e.g.
+/// `{"sensors": [[0.001, 0.001]], "samples": 32, "confidence": 0.95}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseModel {
+    /// standard deviation of (ka, kb) of each sensor; a single one applies to all sensors.
+    pub sensors: Vec<(f64, f64)>,
+
+    /// number of perturbed curvature sets of each frame.
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+
+    /// probability of a point inside its confidence radius.
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+
+    /// publish per-point covariance besides confidence radius.
+    #[serde(default)]
+    pub covariance: bool,
+}
+
+/// Most perturbed curvature sets of each frame, all reconstructed before the next frame.
+const MAX_SAMPLES: usize = 1024;
+
+fn default_samples() -> usize {
+    32
+}
+
+fn default_confidence() -> f64 {
+    0.95
+}
+
+/// Sample of standard normal distribution, by Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u = 1. - rng.gen::<f64>(); // in (0, 1]
+    let v = rng.gen::<f64>();
+    (-2. * u.ln()).sqrt() * (2. * std::f64::consts::PI * v).cos()
+}
+
+impl NoiseModel {
+    /// Load noise model from a json file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let model: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        let valid = !model.sensors.is_empty()
+            && model
+                .sensors
+                .iter()
+                .all(|(a, b)| *a >= 0. && *b >= 0. && a.is_finite() && b.is_finite())
+            && model.samples >= 2
+            && model.samples <= MAX_SAMPLES
+            && model.confidence > 0.
+            && model.confidence < 1.;
+        if valid {
+            Ok(model)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "noise model requires non-negative deviations, 2 to {} samples and confidence in (0, 1)",
+                    MAX_SAMPLES
+                ),
+            ))
+        }
+    }
+
+    /// Add noise to curvature.
+    pub fn perturb(
+        &self,
+        data: &[(f64, f64, f64)],
+        rng: &mut impl Rng,
+    ) -> Result<Vec<(f64, f64, f64)>, ReconstructError> {
+        if self.sensors.len() != 1 && self.sensors.len() != data.len() {
+            return Err(ReconstructError::NoiseMismatch {
+                sensors: self.sensors.len(),
+                found: data.len(),
+            });
+        }
+        Ok(data
+            .iter()
+            .enumerate()
+            .map(|(index, (s, ka, kb))| {
+                let (sigma_a, sigma_b) = self.sensors[index.min(self.sensors.len() - 1)];
+                (
+                    *s,
+                    ka + sigma_a * standard_normal(rng),
+                    kb + sigma_b * standard_normal(rng),
+                )
+            })
+            .collect())
+    }
+
+    /// Reconstruct perturbed curvature sets, and estimate mean and spread of each point.
+    pub fn envelope(
+        &self,
+        data: &[(f64, f64, f64)],
+        reconstruct: impl Fn(&[(f64, f64, f64)]) -> Result<Vec<Point>, ReconstructError>,
+        rng: &mut impl Rng,
+    ) -> Result<Envelope, ReconstructError> {
+        let curves = (0..self.samples)
+            .map(|_| reconstruct(&self.perturb(data, rng)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let position = |p: &Point| Vector3::new(p.x as f64, p.y as f64, p.z as f64);
+        let len = curves.iter().map(Vec::len).min().unwrap_or_default();
+        let n = curves.len() as f64;
+
+        let mut envelope = Envelope {
+            mean: Vec::with_capacity(len),
+            radius: Vec::with_capacity(len),
+            covariance: if self.covariance {
+                Some(Vec::with_capacity(len))
+            } else {
+                None
+            },
+        };
+        let mut distances = Vec::with_capacity(curves.len());
+        for i in 0..len {
+            let mean = curves
+                .iter()
+                .map(|curve| position(&curve[i]))
+                .sum::<Vector3<f64>>()
+                / n;
+            distances.clear();
+            distances.extend(
+                curves
+                    .iter()
+                    .map(|curve| (position(&curve[i]) - mean).norm()),
+            );
+            distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let rank = ((self.confidence * n).ceil() as usize).clamp(1, distances.len());
+
+            envelope
+                .mean
+                .push([mean.x as f32, mean.y as f32, mean.z as f32]);
+            envelope.radius.push(distances[rank - 1] as f32);
+            if let Some(covariance) = envelope.covariance.as_mut() {
+                let c = curves
+                    .iter()
+                    .map(|curve| {
+                        let d = position(&curve[i]) - mean;
+                        d * d.transpose()
+                    })
+                    .sum::<Matrix3<f64>>()
+                    / (n - 1.);
+                covariance.push([
+                    c[(0, 0)] as f32,
+                    c[(0, 1)] as f32,
+                    c[(0, 2)] as f32,
+                    c[(1, 1)] as f32,
+                    c[(1, 2)] as f32,
+                    c[(2, 2)] as f32,
+                ]);
+            }
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NoiseModel, MAX_SAMPLES};
+    use crate::channels::curvature_splines::PointSlice;
+    use crate::channels::error::ReconstructError;
+    use num::{One, Zero};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    fn reconstruct(data: &[(f64, f64, f64)]) -> Result<Vec<crate::curve::Point>, ReconstructError> {
+        data.interpolate(0.05)?
+            .frenet_reconstruct(Zero::zero(), One::one())
+    }
+
+    #[test]
+    fn straight_rod() {
+        let data = [(0., 0., 0.), (10., 0., 0.)];
+        let mut model = NoiseModel {
+            sensors: vec![(0., 0.)],
+            samples: 500,
+            confidence: 0.95,
+            covariance: true,
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+        let envelope = model.envelope(&data, reconstruct, &mut rng).unwrap();
+        assert!(envelope.radius.iter().all(|r| *r == 0.));
+
+        // ka varies linearly between two sensors, tip deflects by -(e0 / 3 + e1 / 6) * L^2.
+        model.sensors = vec![(1e-3, 0.)];
+        let envelope = model.envelope(&data, reconstruct, &mut rng).unwrap();
+        let deviation = 100. * 1e-3 * 5f64.sqrt() / 6.;
+        let tip = envelope.mean.len() - 1;
+        let covariance = envelope.covariance.unwrap();
+        let (sigma, radius) = (
+            (covariance[tip][0] as f64).sqrt(),
+            envelope.radius[tip] as f64,
+        );
+        assert!(
+            (sigma / deviation - 1.).abs() < 0.15,
+            "{} / {}",
+            sigma,
+            deviation
+        );
+        assert!(
+            (radius / (1.96 * deviation) - 1.).abs() < 0.15,
+            "{} / {}",
+            radius,
+            deviation
+        );
+        assert!(envelope.mean[tip][0].abs() < 0.01 && envelope.mean[tip][1].abs() < 1e-6);
+        assert!(envelope.radius.windows(2).filter(|r| r[1] < r[0]).count() < tip / 10);
+    }
+
+    #[test]
+    fn mismatch() {
+        let model = NoiseModel {
+            sensors: vec![(1e-3, 1e-3); 2],
+            samples: 2,
+            confidence: 0.5,
+            covariance: false,
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(
+            Some(ReconstructError::NoiseMismatch {
+                sensors: 2,
+                found: 3
+            }),
+            model
+                .envelope(
+                    &[(0., 0., 0.), (1., 0., 0.), (2., 0., 0.)],
+                    reconstruct,
+                    &mut rng
+                )
+                .err()
+        );
+    }
+
+    #[test]
+    fn load() {
+        let path = std::env::temp_dir().join("crow-noise-load.json");
+        std::fs::write(&path, r#"{"sensors": [[0.001, 0.002]]}"#).unwrap();
+        let model = NoiseModel::load(&path).unwrap();
+        assert_eq!(vec![(0.001, 0.002)], model.sensors);
+        assert_eq!(32, model.samples);
+
+        // each frame is reconstructed once per sample, so too many samples stall the channel.
+        let samples = format!(
+            r#"{{"sensors": [[0.001, 0.002]], "samples": {}}}"#,
+            MAX_SAMPLES + 1
+        );
+        std::fs::write(&path, samples).unwrap();
+        let result = NoiseModel::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
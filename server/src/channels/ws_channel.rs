@@ -1,47 +1,179 @@
-use super::curvature_splines::PointSlice;
+use super::fbg::StrainModel;
+use super::protocol::{BinaryFrame, Frame, Units};
+use super::shape::Pose;
 use super::SyncChannel;
-use crate::curve::Curve;
 
 use futures::{stream::SplitStream, StreamExt};
-use libflate::deflate::Encoder;
 use log::{debug, error, info, warn};
-use num::{one, zero};
 use roa::websocket::tungstenite::Error as WsError;
 use roa::websocket::{Message, SocketStream};
+use serde::Deserialize;
+use serde_json::Value;
 
-use std::io::Write;
-use std::time::{SystemTime, UNIX_EPOCH};
+/// Command from source client, e.g. `{"command": "tare"}`.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum Command {
+    /// Record the current reading as the straight baseline.
+    #[serde(alias = "zero")]
+    Tare,
+
+    /// Base pose from a tracker, with timestamp in milliseconds since unix epoch,
+    /// default to time of arrival.
+    Pose {
+        position: [f64; 3],
+        // quaternion [w, x, y, z]
+        orientation: [f64; 4],
+        #[serde(default)]
+        timestamp: Option<u64>,
+    },
+}
+
+/// Parse stations of a frame, as readings of cores if the channel has a strain model,
+/// then convert them from units of frame to units of channel.
+///
+/// Missing values are null, and imputed by policy of the channel.
+fn stations(
+    channel: &SyncChannel,
+    units: &Units,
+    frame: Value,
+) -> Result<Vec<(f64, f64, f64)>, String> {
+    let strain = channel.config().strain.as_ref();
+    if strain.is_some() && units.curvature.is_some() {
+        return Err("unit of curvature does not apply to core readings".to_string());
+    }
+    let mut data = readings(strain, frame)?;
+    units.apply(&mut data, channel.config().unit);
+    Ok(data)
+}
+
+/// Parse stations `(s, ka, kb)` of a frame, as readings of cores if there is a strain model.
+fn readings(strain: Option<&StrainModel>, frame: Value) -> Result<Vec<(f64, f64, f64)>, String> {
+    let missing = |value: Option<f64>| value.unwrap_or(f64::NAN);
+    let wrong = |_| "wrong data from source client".to_string();
+    match strain {
+        None => Ok(
+            serde_json::from_value::<Vec<(f64, Option<f64>, Option<f64>)>>(frame)
+                .map_err(wrong)?
+                .into_iter()
+                .map(|(s, ka, kb)| (s, missing(ka), missing(kb)))
+                .collect(),
+        ),
+        Some(model) => {
+            let strain = serde_json::from_value::<Vec<Vec<Option<f64>>>>(frame)
+                .map_err(wrong)?
+                .into_iter()
+                .map(|station| station.into_iter().map(missing).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            model
+                .curvature(&strain)
+                .map_err(|err| format!("reject frame from source client: {}", err))
+        }
+    }
+}
 
 pub async fn handle(
     channel: SyncChannel,
     mut stream: SplitStream<SocketStream>,
 ) -> Result<(), WsError> {
     async fn response(channel: SyncChannel, raw_data: &[u8]) {
-        let data: Vec<(f64, f64, f64)> = match serde_json::from_slice(raw_data) {
-            Ok(d) => d,
-            Err(_) => {
-                error!("wrong data from source client");
+        if let Ok(command) = serde_json::from_slice::<Command>(raw_data) {
+            match command {
+                Command::Tare if channel.tare().await => info!("tare channel"),
+                Command::Tare => warn!("no frame to tare"),
+                Command::Pose {
+                    position,
+                    orientation,
+                    timestamp,
+                } => match Pose::new(position, orientation) {
+                    Ok(pose) => channel.track(pose, timestamp).await,
+                    Err(err) => error!("reject pose from source client: {}", err),
+                },
+            }
+            return;
+        }
+
+        let frame = match Frame::parse(raw_data) {
+            Ok(frame) => frame,
+            Err(err) => {
+                error!("{}", err);
                 return;
             }
         };
+        match &channel.config().structure {
+            None => {
+                let data = match stations(&channel, &frame.units, frame.samples) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        error!("{}", err);
+                        return;
+                    }
+                };
+                match channel.reconstruct(data, &frame.header).await {
+                    Ok(curve) => {
+                        if !curve.imputed.is_empty() {
+                            debug!("impute stations {:?}", curve.imputed);
+                        }
+                        channel.publish(&curve).await
+                    }
+                    Err(err) => error!("reject frame from source client: {}", err),
+                }
+            }
+            // one frame of stations per segment, in order of the structure.
+            Some(_) => {
+                let units = frame.units;
+                let data = serde_json::from_value::<Vec<Value>>(frame.samples)
+                    .map_err(|_| "wrong data from source client".to_string())
+                    .and_then(|segments| {
+                        segments
+                            .into_iter()
+                            .map(|segment| stations(&channel, &units, segment))
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                let data = match data {
+                    Ok(data) => data,
+                    Err(err) => {
+                        error!("{}", err);
+                        return;
+                    }
+                };
+                match channel.reconstruct_tree(data, &frame.header).await {
+                    Ok(curve) => {
+                        if !curve.imputed.is_empty() {
+                            debug!("impute stations {:?}", curve.imputed);
+                        }
+                        channel.publish(&curve).await
+                    }
+                    Err(err) => error!("reject frame from source client: {}", err),
+                }
+            }
+        }
+    }
 
-        let points = data
-            .interpolate(0.05)
-            .frenet_reconstruct(zero(), one())
-            .unwrap();
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        let curve = Curve { timestamp, points };
-        let mut encoder = Encoder::new(Vec::new());
-        encoder
-            .write_all(&serde_json::to_vec(&curve).unwrap())
-            .unwrap();
-        channel
-            .broadcast(Message::Binary(encoder.finish().into_result().unwrap()))
-            .await;
-    };
+    /// Reconstruct a binary frame, of stations of a single curve.
+    async fn binary(channel: SyncChannel, raw_data: &[u8]) {
+        let config = channel.config();
+        if config.strain.is_some() || config.structure.is_some() {
+            error!("binary frame carries curvature of a single curve, neither cores nor segments");
+            return;
+        }
+        let frame = match BinaryFrame::parse(raw_data) {
+            Ok(frame) => frame,
+            Err(err) => {
+                error!("{}", err);
+                return;
+            }
+        };
+        match channel.reconstruct(frame.samples, &frame.header).await {
+            Ok(curve) => {
+                if !curve.imputed.is_empty() {
+                    debug!("impute stations {:?}", curve.imputed);
+                }
+                channel.publish(&curve).await
+            }
+            Err(err) => error!("reject frame from source client: {}", err),
+        }
+    }
 
     while let Some(message) = stream.next().await {
         let message = message?;
@@ -52,6 +184,9 @@ pub async fn handle(
             }
             Message::Ping(ref data) => info!("client ping: {}", String::from_utf8_lossy(data)),
             Message::Pong(ref data) => warn!("ignored pong: {}", String::from_utf8_lossy(data)),
+            Message::Binary(ref data) if BinaryFrame::is_binary(data) => {
+                binary(channel.clone(), data.as_slice()).await
+            }
             Message::Binary(ref data) => response(channel.clone(), data.as_slice()).await,
             Message::Text(ref data) => response(channel.clone(), data.as_bytes()).await,
         }
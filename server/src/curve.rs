@@ -1,14 +1,302 @@
+use nalgebra::{Matrix3, Quaternion, Rotation3, UnitQuaternion, Vector3};
 use serde::Serialize;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 
 #[derive(Debug, Serialize)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
     pub z: f32,
+
+    // orientation of local frame, omitted unless the channel emits frames.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<Frame>,
+}
+
+/// Orientation of local frame at a point, in absolute coordinate.
+///
+/// Tangent is the z axis of local frame, and normal is its -x axis,
+/// toward which a positive ka bends the curve.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frame {
+    /// Unit quaternion rotating local frame to absolute frame, as [x, y, z, w].
+    Quaternion([f32; 4]),
+
+    /// Unit tangent, normal and binormal vectors.
+    Tnb {
+        tangent: [f32; 3],
+        normal: [f32; 3],
+        binormal: [f32; 3],
+    },
+
+    /// Rotation matrix mapping local frame to absolute frame, in column-major order.
+    ///
+    /// Reconstructors keep frames as matrices, converted only if the channel emits frames.
+    Matrix([f32; 9]),
+}
+
+/// Representation of frames emitted by a channel.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum FrameFormat {
+    #[default]
+    None,
+    Quaternion,
+    Tnb,
 }
 
 #[derive(Debug, Serialize)]
 pub struct Curve {
     pub timestamp: u64,
+
+    // sequence number of the frame at source, omitted if source does not number frames.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
     pub points: Vec<Point>,
+
+    // uncertainty of points, omitted unless the channel has a noise model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<Envelope>,
+
+    // indices of stations whose missing values are imputed, omitted if none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub imputed: Vec<usize>,
+
+    // error of tip against its target, omitted unless the channel has a target of tip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closure: Option<Closure>,
+}
+
+/// A curve of a multi-segment structure.
+#[derive(Debug, Serialize)]
+pub struct CurveSegment {
+    pub name: String,
+
+    // index of parent segment, the base of channel if None.
+    pub parent: Option<usize>,
+    pub points: Vec<Point>,
+}
+
+/// Frame of a multi-segment structure, segments in order of the structure.
+#[derive(Debug, Serialize)]
+pub struct MultiCurve {
+    pub timestamp: u64,
+
+    // sequence number of the frame at source, omitted if source does not number frames.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    pub segments: Vec<CurveSegment>,
+
+    // indices of imputed stations, counted over all segments, omitted if none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub imputed: Vec<usize>,
+}
+
+/// Uncertainty of a curve estimated by Monte-Carlo.
+#[derive(Debug, Serialize)]
+pub struct Envelope {
+    /// mean position of each point.
+    pub mean: Vec<[f32; 3]>,
+
+    /// distance from the mean within which a point falls at the confidence level.
+    pub radius: Vec<f32>,
+
+    /// covariance of each point, as [xx, xy, xz, yy, yz, zz].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub covariance: Option<Vec<[f32; 6]>>,
+}
+
+/// Closure error between the tip reconstructed from the base and the target of tip.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Closure {
+    /// error of the tip reconstructed from the base, before correction.
+    pub initial: TipError,
+
+    /// error left at the tip after correction.
+    pub remaining: TipError,
+}
+
+/// Error of a tip against its target, omitting parts without target.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct TipError {
+    /// distance between positions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<f64>,
+
+    /// angle between orientations, in radians.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub angle: Option<f64>,
+}
+
+impl Frame {
+    /// Frame of a rotation mapping local frame to absolute frame.
+    pub fn from_rotation(rotation: &UnitQuaternion<f64>) -> Self {
+        let q = rotation.coords;
+        Frame::Quaternion([q[0] as f32, q[1] as f32, q[2] as f32, q[3] as f32])
+    }
+
+    /// Frame of a rotation matrix mapping local frame to absolute frame, kept as is.
+    pub fn from_matrix(rotation: &Rotation3<f64>) -> Self {
+        let mut matrix = [0.; 9];
+        for (value, element) in matrix.iter_mut().zip(rotation.matrix().iter()) {
+            *value = *element as f32;
+        }
+        Frame::Matrix(matrix)
+    }
+
+    /// Rotation mapping local frame to absolute frame.
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        match *self {
+            Frame::Matrix(matrix) => UnitQuaternion::from_rotation_matrix(
+                &Rotation3::from_matrix_unchecked(Matrix3::from_column_slice(&matrix)),
+            ),
+            Frame::Quaternion([x, y, z, w]) => {
+                UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+            }
+            Frame::Tnb {
+                tangent,
+                normal,
+                binormal,
+            } => {
+                let column = |v: [f32; 3]| Vector3::new(v[0], v[1], v[2]);
+                let mut rotation = Rotation3::from_matrix_unchecked(Matrix3::from_columns(&[
+                    -column(normal),
+                    -column(binormal),
+                    column(tangent),
+                ]));
+                rotation.renormalize();
+                UnitQuaternion::from_rotation_matrix(&rotation)
+            }
+        }
+    }
+
+    /// Convert to tangent, normal and binormal vectors.
+    pub fn to_tnb(self) -> Self {
+        let rotation = self.rotation();
+        let array = |v: Vector3<f32>| [v[0], v[1], v[2]];
+        Frame::Tnb {
+            tangent: array(rotation * Vector3::z()),
+            normal: array(rotation * -Vector3::x()),
+            binormal: array(rotation * -Vector3::y()),
+        }
+    }
+
+    /// Convert to quaternion.
+    pub fn to_quaternion(self) -> Self {
+        let q = self.rotation().coords;
+        Frame::Quaternion([q[0], q[1], q[2], q[3]])
+    }
+}
+
+impl FrameFormat {
+    /// Convert frames of points to this format, or strip them.
+    pub fn apply(self, points: &mut [Point]) {
+        for point in points {
+            point.frame = match (self, point.frame) {
+                (FrameFormat::None, _) | (_, None) => None,
+                (FrameFormat::Quaternion, Some(frame)) => Some(frame.to_quaternion()),
+                (FrameFormat::Tnb, Some(frame)) => Some(frame.to_tnb()),
+            }
+        }
+    }
+}
+
+impl FromStr for FrameFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(FrameFormat::None),
+            "quaternion" => Ok(FrameFormat::Quaternion),
+            "tnb" => Ok(FrameFormat::Tnb),
+            _ => Err(format!("unknown frame format: {}", s)),
+        }
+    }
+}
+
+impl Display for FrameFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FrameFormat::None => "none",
+            FrameFormat::Quaternion => "quaternion",
+            FrameFormat::Tnb => "tnb",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, FrameFormat, Point};
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    fn point(frame: Frame) -> Point {
+        Point {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            frame: Some(frame),
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let rotation =
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f64::consts::PI / 2.);
+        let mut points = vec![point(Frame::from_rotation(&rotation))];
+        FrameFormat::None.apply(&mut points);
+        assert_eq!(
+            r#"[{"x":0.0,"y":0.0,"z":0.0}]"#,
+            serde_json::to_string(&points).unwrap()
+        );
+
+        let mut points = vec![point(Frame::from_rotation(&rotation))];
+        FrameFormat::Quaternion.apply(&mut points);
+        assert!(serde_json::to_string(&points)
+            .unwrap()
+            .starts_with(r#"[{"x":0.0,"y":0.0,"z":0.0,"frame":{"quaternion":["#));
+
+        FrameFormat::Tnb.apply(&mut points);
+        assert!(serde_json::to_string(&points)
+            .unwrap()
+            .starts_with(r#"[{"x":0.0,"y":0.0,"z":0.0,"frame":{"tnb":{"tangent":["#));
+    }
+
+    #[test]
+    fn convert() {
+        // tangent along absolute x, normal along absolute z.
+        let rotation =
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f64::consts::PI / 2.);
+        let frame = Frame::from_rotation(&rotation);
+        match frame.to_tnb() {
+            Frame::Tnb {
+                tangent,
+                normal,
+                binormal,
+            } => {
+                let close = |u: [f32; 3], v: [f32; 3]| {
+                    u.iter().zip(v.iter()).all(|(a, b)| (a - b).abs() < 1e-6)
+                };
+                assert!(close([1., 0., 0.], tangent), "{:?}", tangent);
+                assert!(close([0., 0., 1.], normal), "{:?}", normal);
+                assert!(close([0., -1., 0.], binormal), "{:?}", binormal);
+            }
+            _ => unreachable!(),
+        }
+        assert!(frame.rotation().angle_to(&frame.to_tnb().rotation()) < 1e-5);
+        assert!(
+            frame
+                .rotation()
+                .angle_to(&frame.to_tnb().to_quaternion().rotation())
+                < 1e-5
+        );
+
+        let mut points = vec![point(Frame::from_matrix(&rotation.to_rotation_matrix()))];
+        FrameFormat::Quaternion.apply(&mut points);
+        let quaternion = points[0].frame.unwrap();
+        assert!(matches!(quaternion, Frame::Quaternion(_)));
+        assert!(frame.rotation().angle_to(&quaternion.rotation()) < 1e-5);
+        assert_eq!("tnb".parse(), Ok(FrameFormat::Tnb));
+        assert!("matrix".parse::<FrameFormat>().is_err());
+    }
 }
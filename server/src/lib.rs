@@ -0,0 +1,4 @@
+//! Realtime reconstruction of 3D curves from curvature of fibre sensors.
+
+pub mod channels;
+pub mod curve;
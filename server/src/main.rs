@@ -1,55 +1,310 @@
-mod channels;
-mod curve;
-
-use channels::mock::cos_channel;
-use channels::ws_channel;
-use channels::SyncChannels;
 use futures::{stream::SplitStream, SinkExt, StreamExt, TryFutureExt};
 use log::{debug, error, info, warn};
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
 use roa::cors::Cors;
-use roa::http::Method;
+use roa::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use roa::http::{Method, StatusCode};
 use roa::logger::logger;
 use roa::preload::*;
+use roa::query::query_parser;
 use roa::router::{allow, Router};
 use roa::websocket::tungstenite::protocol::frame::{coding::CloseCode, CloseFrame};
 use roa::websocket::tungstenite::Error as WsError;
 use roa::websocket::{Message, SocketStream, Websocket};
-use roa::{App, Context, Next};
+use roa::{status, App, Context, Next};
+use server::channels::anchor::{Anchor, Correction, Target};
+use server::channels::calibration::Calibration;
+use server::channels::fbg::StrainModel;
+use server::channels::mock::{cos_channel, cos_config};
+use server::channels::protocol::Encoding;
+use server::channels::reconstructor::{Cosserat, Registry};
+use server::channels::rod::Rod;
+use server::channels::structure::Structure;
+use server::channels::uncertainty::NoiseModel;
+use server::channels::ws_channel;
+use server::channels::{ChannelConfig, Step, SyncChannels};
 use std::borrow::Cow;
 use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[async_std::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv()?;
     pretty_env_logger::init();
     let server_addr = env::var("CROW_SERVER_ADDR")?;
-    let channels = SyncChannels::new();
-    cos_channel(channels.new_channel().await.1);
+    let channels = SyncChannels::new(Registry::default());
+    cos_channel(channels.new_channel(cos_config()).await.1);
 
     let downstream_router = Router::new().gate(subscribe_guard).on(
         "/",
         allow([Method::GET], Websocket::new(handle_downstream_client)),
     );
+    let upstream_router = Router::new()
+        .gate(upstream_guard)
+        .on("/", Websocket::new(handle_upstream_client));
     let router = Router::new()
-        .on("upstream", Websocket::new(handle_upstream_client))
+        .include("/upstream", upstream_router)
         .include("/downstream/:id", downstream_router);
     App::state(channels)
         .gate(logger)
         .gate(Cors::new())
+        .gate(query_parser)
         .end(router.routes("/")?)
         .listen(server_addr, |addr| info!("Server is listening on {}", addr))?
         .await?;
     Ok(())
 }
 
+/// Scope of variables stored by `subscribe_guard`.
+struct SubscribeScope;
+
+/// Check the channel, then choose encoding by query `encoding`, or by websocket subprotocol.
 async fn subscribe_guard(ctx: &mut Context<SyncChannels>, next: Next<'_>) -> roa::Result<()> {
     let index: usize = ctx.must_param("id")?.parse()?;
     ctx.get_channel(index).await?;
+    let subprotocol = ctx
+        .req
+        .headers
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|protocols| protocols.to_str().ok())
+        .and_then(Encoding::negotiate);
+    let encoding = match ctx.query("encoding") {
+        Some(encoding) => encoding.parse()?,
+        None => subprotocol.unwrap_or_default(),
+    };
+    ctx.store_scoped(SubscribeScope, "encoding", encoding);
+    next.await?;
+    // accept the subprotocol only if it is the chosen encoding.
+    if subprotocol == Some(encoding) {
+        ctx.resp.headers.insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(encoding.subprotocol()),
+        );
+    }
+    Ok(())
+}
+
+/// Scope of variables stored by `upstream_guard`.
+struct UpstreamScope;
+
+/// Parse channel config from query, e.g. `?algorithm=frenet&interpolation=akima&ds=0.05`.
+async fn upstream_guard(ctx: &mut Context<SyncChannels>, next: Next<'_>) -> roa::Result<()> {
+    let mut config = ChannelConfig::default();
+    if let Some(name) = ctx.query("algorithm") {
+        config.reconstructor = match ctx.reconstructor(&name) {
+            Some(reconstructor) => reconstructor,
+            None => {
+                return Err(status!(
+                    StatusCode::BAD_REQUEST,
+                    format!("unknown algorithm: {}", &*name)
+                ))
+            }
+        };
+    }
+    let mut rod = None;
+    if let Some(stiffness) = ctx.query("stiffness") {
+        let v = parse_floats(&stiffness, 2)?;
+        if !(v[0] > 0. && v[1] > 0.) {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "stiffness should be positive"
+            ));
+        }
+        rod.get_or_insert_with(Rod::default).stiffness = (v[0], v[1]);
+    }
+    if let Some(regularization) = ctx.query("regularization") {
+        let regularization: f64 = regularization.parse()?;
+        if !(regularization >= 0. && regularization.is_finite()) {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "regularization should be non-negative"
+            ));
+        }
+        rod.get_or_insert_with(Rod::default).regularization = regularization;
+    }
+    if let Some(tip) = ctx.query("tip-load") {
+        rod.get_or_insert_with(Rod::default).tip = tip.parse()?;
+    }
+    if let Some(rod) = rod {
+        if ctx.query("algorithm").as_deref().map(String::as_str) != Some("cosserat") {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "stiffness, regularization and tip-load need algorithm cosserat"
+            ));
+        }
+        config.reconstructor = Arc::new(Cosserat(rod));
+    }
+    if let Some(scheme) = ctx.query("interpolation") {
+        config.scheme = scheme.parse()?;
+    }
+    if let Some(name) = ctx.query("cores") {
+        config.strain = Some(
+            StrainModel::load(calibration_path(&name)?)
+                .map_err(|err| load_error("core geometry", &name, err))?,
+        );
+    }
+    if let Some(imputation) = ctx.query("impute") {
+        config.imputation = imputation.parse()?;
+    }
+    if let Some(name) = ctx.query("calibration") {
+        config.calibration = Calibration::load(calibration_path(&name)?)
+            .map_err(|err| load_error("calibration", &name, err))?;
+    }
+    if let Some(name) = ctx.query("noise") {
+        config.noise = Some(
+            NoiseModel::load(calibration_path(&name)?)
+                .map_err(|err| load_error("noise model", &name, err))?,
+        );
+    }
+    if let Some(name) = ctx.query("structure") {
+        config.structure = Some(
+            Structure::load(calibration_path(&name)?)
+                .map_err(|err| load_error("structure", &name, err))?,
+        );
+    }
+    if let Some(filter) = ctx.query("filter") {
+        config.filter = filter.parse()?;
+    }
+    if let Some(frame) = ctx.query("frame") {
+        config.frame = frame.parse()?;
+    }
+    if let Some(unit) = ctx.query("unit") {
+        config.unit = Some(unit.parse()?);
+    }
+    if let Some(length) = ctx.query("length") {
+        let length: f64 = length.parse()?;
+        if !(length > 0. && length.is_finite()) {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "length should be positive"
+            ));
+        }
+        config.device.length = Some(length);
+    }
+    if let Some(offset) = ctx.query("offset") {
+        config.device.offset = offset.parse()?;
+        if !(config.device.offset >= 0. && config.device.offset.is_finite()) {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "offset should be non-negative"
+            ));
+        }
+    }
+    if let Some(extrapolation) = ctx.query("extrapolation") {
+        config.device.extrapolation = extrapolation.parse()?;
+    }
+    if let Some(ds) = ctx.query("ds") {
+        let ds: f64 = ds.parse()?;
+        if !(ds > 0. && ds.is_finite()) {
+            return Err(status!(StatusCode::BAD_REQUEST, "ds should be positive"));
+        }
+        config.step = Step::Fixed(ds);
+    }
+    if let Some(tolerance) = ctx.query("tolerance") {
+        let tolerance: f64 = tolerance.parse()?;
+        if !(tolerance > 0. && tolerance.is_finite()) {
+            return Err(status!(
+                StatusCode::BAD_REQUEST,
+                "tolerance should be positive"
+            ));
+        }
+        config.step = config.step.adaptive(tolerance);
+    }
+    let mut target = Target::default();
+    if let Some(position) = ctx.query("tip-position") {
+        let v = parse_floats(&position, 3)?;
+        target.position = Some(Vector3::new(v[0], v[1], v[2]));
+    }
+    if let Some(orientation) = ctx.query("tip-orientation") {
+        target.orientation = Some(parse_quaternion(&orientation)?);
+    }
+    let correction: Option<Correction> = match ctx.query("closure") {
+        Some(correction) => Some(correction.parse()?),
+        None => None,
+    };
+    if target != Target::default() {
+        config.anchor = Some(Anchor {
+            target,
+            correction: correction.unwrap_or_default(),
+        });
+    } else if correction.is_some() {
+        return Err(status!(
+            StatusCode::BAD_REQUEST,
+            "closure needs tip-position or tip-orientation"
+        ));
+    }
+    if let Some(position) = ctx.query("position") {
+        let v = parse_floats(&position, 3)?;
+        config.ai = Vector3::new(v[0], v[1], v[2]);
+    }
+    if let Some(orientation) = ctx.query("orientation") {
+        // orientation of base frame as quaternion (w, x, y, z); ri is its inverse.
+        let orientation = parse_quaternion(&orientation)?;
+        config.ri = *orientation.inverse().to_rotation_matrix().matrix();
+    }
+    ctx.store_scoped(UpstreamScope, "config", config);
     next.await
 }
 
+/// Path of `<name>.json` in directory `CROW_CALIBRATION_DIR`, default to `calibration`.
+fn calibration_path(name: &str) -> roa::Result<PathBuf> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(status!(
+            StatusCode::BAD_REQUEST,
+            format!("invalid file name: {}", name)
+        ));
+    }
+    let dir = env::var("CROW_CALIBRATION_DIR").unwrap_or_else(|_| "calibration".to_string());
+    Ok(PathBuf::from(dir).join(format!("{}.json", name)))
+}
+
+fn load_error(kind: &str, name: &str, err: std::io::Error) -> roa::Status {
+    status!(
+        StatusCode::BAD_REQUEST,
+        format!("fail to load {} {}: {}", kind, name, err)
+    )
+}
+
+/// Parse comma separated floats.
+fn parse_floats(value: &str, len: usize) -> roa::Result<Vec<f64>> {
+    let floats = value
+        .split(',')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| status!(StatusCode::BAD_REQUEST, err))?;
+    if floats.len() != len || floats.iter().any(|v| !v.is_finite()) {
+        return Err(status!(
+            StatusCode::BAD_REQUEST,
+            format!("expect {} finite numbers, got `{}`", len, value)
+        ));
+    }
+    Ok(floats)
+}
+
+/// Parse quaternion `w,x,y,z` into a rotation, which should not be zero.
+fn parse_quaternion(value: &str) -> roa::Result<UnitQuaternion<f64>> {
+    let q = parse_floats(value, 4)?;
+    let quaternion = Quaternion::new(q[0], q[1], q[2], q[3]);
+    if quaternion.norm() < 1e-9 {
+        return Err(status!(
+            StatusCode::BAD_REQUEST,
+            format!("quaternion should not be zero, got `{}`", value)
+        ));
+    }
+    Ok(UnitQuaternion::from_quaternion(quaternion))
+}
+
 async fn handle_upstream_client(ctx: Context<SyncChannels>, stream: SocketStream) {
-    let (index, channel) = ctx.new_channel().await;
+    let config = ctx
+        .load_scoped::<UpstreamScope, ChannelConfig>("config")
+        .map(|config| ChannelConfig::clone(&config))
+        .unwrap_or_default();
+    let (index, channel) = ctx.new_channel(config).await;
     let (mut sender, receiver) = stream.split();
     let result = sender
         .send(Message::Text(
@@ -68,7 +323,11 @@ async fn handle_downstream_client(ctx: Context<SyncChannels>, stream: SocketStre
     let channel = ctx.get_channel(index).await.unwrap();
 
     let (sender, receiver) = stream.split();
-    let index = channel.register(sender).await;
+    let encoding = ctx
+        .load_scoped::<SubscribeScope, Encoding>("encoding")
+        .map(|encoding| *encoding)
+        .unwrap_or_default();
+    let index = channel.register(sender, encoding).await;
     let result = handle_downstream_message(receiver).await;
     if let Some(mut sender) = channel.deregister(index).await {
         if let Err(err) = result {